@@ -0,0 +1,68 @@
+/*
+ * C API of libstk1
+ *
+ * Build `libstk1.so` or `libstk1.a` with `cargo build --release -p libstk1-capi`.
+ *
+ * LICENSE: PUBLIC DOMAIN
+ */
+
+#ifndef STK1_H
+#define STK1_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* tek1 functions use the same calling convention as extra/x86/stk1.asm on 32-bit x86 */
+#if defined(__i386__) && (defined(__GNUC__) || defined(__clang__))
+#define STK1_FASTCALL __attribute__((fastcall))
+#elif defined(_M_IX86)
+#define STK1_FASTCALL __fastcall
+#else
+#define STK1_FASTCALL
+#endif
+
+/* Error codes */
+#define STK1_ERROR_INVALID_DATA -1
+#define STK1_ERROR_INPUT_TOO_LARGE -2
+#define STK1_ERROR_OUTPUT_TOO_SMALL -3
+#define STK1_ERROR_OUT_OF_MEMORY -4
+#define STK1_ERROR_INVALID_ARGUMENT -5
+
+/* Returns the maximum length of data compressed from `len` bytes. */
+size_t stk1_max_compressed_len(size_t len);
+
+/*
+ * Compresses `input` into `output` at `level` (1 to 9, or 0 for the default)
+ * and returns the compressed length, or a negative error code.
+ * An output of `stk1_max_compressed_len(input_len)` bytes is always large enough.
+ */
+ptrdiff_t stk1_encode(const void *input, size_t input_len, void *output, size_t output_len,
+                      int level);
+
+/*
+ * Decompresses `input` into `output`, whose length must be the decompressed size,
+ * and returns zero, or a negative error code.
+ */
+ptrdiff_t stk1_decode(const void *input, size_t input_len, void *output, size_t output_len);
+
+/*
+ * Returns the decompressed size of the tek1 data of `size` bytes at `p`,
+ * -2 if the signature differs only in the first byte, which marks other variants of tek,
+ * or -1 if the data is not tek1 or the header is invalid.
+ */
+int STK1_FASTCALL tek1_checkformat(int size, const void *p);
+
+/*
+ * Decompresses the tek1 data at `p` into `q` and returns zero, or 1 if the data is invalid.
+ * The data must be valid up to its last token; use `stk1_decode` for untrusted data.
+ */
+int STK1_FASTCALL tek1_decode(const void *p, void *q);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* STK1_H */
@@ -0,0 +1,6 @@
+//! Shared and static C libraries of libstk1
+//!
+//! `cargo build --release -p libstk1-capi` builds `libstk1.so` and `libstk1.a`,
+//! whose functions are declared in `include/stk1.h`.
+
+pub use libstk1::capi::*;
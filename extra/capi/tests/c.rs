@@ -0,0 +1,62 @@
+use std::{env, path::PathBuf, process::Command};
+
+/// Builds `tests/test.c` against the static library and runs it.
+#[test]
+fn c_test() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // The test executable is in `target/<profile>/deps`, next to which the libraries are built.
+    let exe = env::current_exe().unwrap();
+    let profile_dir = exe.parent().unwrap().parent().unwrap();
+    let library = profile_dir.join("libstk1.a");
+    // `cargo test` builds only the test harness, so build the static library with the same profile.
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let mut build = Command::new(&cargo);
+    build
+        .args(["build", "--quiet", "-p", "libstk1-capi", "--target-dir"])
+        .arg(profile_dir.parent().unwrap())
+        .current_dir(&manifest_dir);
+    match profile_dir.file_name().unwrap().to_str().unwrap() {
+        "debug" => {}
+        "release" => {
+            build.arg("--release");
+        }
+        profile => {
+            build.args(["--profile", profile]);
+        }
+    }
+    if !build.status().is_ok_and(|v| v.success()) || !library.exists() {
+        eprintln!("skipped: {} could not be built", library.display());
+        return;
+    }
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".into());
+    let output = profile_dir.join("stk1-capi-test");
+
+    let status = match Command::new(&cc)
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/test.c"))
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm"])
+        .arg("-o")
+        .arg(&output)
+        .status()
+    {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("skipped: {} is not available: {}", cc, e);
+            return;
+        }
+    };
+    assert!(status.success());
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    assert_eq!(result.stdout, b"ok\n");
+}
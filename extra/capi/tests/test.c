@@ -0,0 +1,98 @@
+/* Test program of the C API, which returns zero on success */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "stk1.h"
+
+#define CHECK(cond)                                                            \
+    do {                                                                       \
+        if (!(cond)) {                                                         \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,   \
+                    #cond);                                                    \
+            return 1;                                                          \
+        }                                                                      \
+    } while (0)
+
+static const unsigned char SIGNATURE[16] = {
+    0x83, 0xff, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00,
+    'O',  'S',  'A',  'S',  'K',  'C',  'M',  'P',
+};
+
+/* Writes `value` in S7s and returns the number of bytes written. */
+static size_t write_s7s(unsigned char *p, size_t value) {
+    size_t len = 1;
+    size_t i;
+    while (len < 10 && (value >> (7 * len)) != 0) {
+        len++;
+    }
+    for (i = 0; i < len; i++) {
+        p[i] = (unsigned char)((value >> (7 * (len - 1 - i))) << 1);
+    }
+    p[len - 1] |= 1;
+    return len;
+}
+
+int main(void) {
+    const char *text = "The quick brown fox jumps over the lazy dog. ";
+    size_t size = strlen(text) * 100;
+    size_t max_len = stk1_max_compressed_len(size);
+    unsigned char *input = malloc(size);
+    unsigned char *output = malloc(size);
+    unsigned char *tek = malloc(32 + max_len);
+    size_t i;
+    size_t header_len;
+    size_t bsiz = 0;
+    ptrdiff_t len;
+
+    CHECK(input && output && tek);
+    for (i = 0; i < size; i++) {
+        input[i] = (unsigned char)text[i % strlen(text)];
+    }
+    CHECK(stk1_max_compressed_len(0) == 0);
+    CHECK(max_len >= size);
+
+    /* stk1 */
+    len = stk1_encode(input, size, tek, max_len, 9);
+    CHECK(len > 0 && (size_t)len < size / 10);
+    CHECK(stk1_decode(tek, (size_t)len, output, size) == 0);
+    CHECK(memcmp(input, output, size) == 0);
+    CHECK(stk1_decode(tek, (size_t)len - 1, output, size) == STK1_ERROR_INVALID_DATA);
+    CHECK(stk1_encode(input, size, tek, 4, 0) == STK1_ERROR_OUTPUT_TOO_SMALL);
+    CHECK(stk1_encode(input, size, tek, max_len, 10) == STK1_ERROR_INVALID_ARGUMENT);
+    CHECK(stk1_encode(NULL, 1, tek, max_len, 0) == STK1_ERROR_INVALID_ARGUMENT);
+    CHECK(stk1_encode(NULL, 0, NULL, 0, 0) == 0);
+
+    /* tek1: signature, size, flags with the block size, zero and data */
+    memcpy(tek, SIGNATURE, sizeof(SIGNATURE));
+    header_len = sizeof(SIGNATURE);
+    header_len += write_s7s(tek + header_len, size);
+    while (((size_t)1 << (bsiz + 8)) < size) {
+        bsiz++;
+    }
+    header_len += write_s7s(tek + header_len, 1 | (bsiz << 1));
+    header_len += write_s7s(tek + header_len, 0);
+    len = stk1_encode(input, size, tek + header_len, max_len, 0);
+    CHECK(len > 0);
+
+    CHECK(tek1_checkformat((int)(header_len + (size_t)len), tek) == (int)size);
+    CHECK(tek1_checkformat(16, tek) == -1);
+    /* Plain data, and another variant of tek */
+    CHECK(tek1_checkformat((int)size, input) == -1);
+    tek[0] = 0x89;
+    CHECK(tek1_checkformat((int)(header_len + (size_t)len), tek) == -2);
+    tek[0] = SIGNATURE[0];
+    memset(output, 0, size);
+    CHECK(tek1_decode(tek, output) == 0);
+    CHECK(memcmp(input, output, size) == 0);
+    tek[8] = 'X';
+    CHECK(tek1_checkformat((int)(header_len + (size_t)len), tek) == -1);
+    CHECK(tek1_decode(tek, output) == 1);
+
+    free(input);
+    free(output);
+    free(tek);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,253 @@
+//! pack, unpack and list commands
+
+use crate::{codec, inspect::json_string, Failure, Options};
+use libstk1::{Archive, ArchiveReader, ArchiveWriter};
+use std::{
+    fmt::Write as _,
+    fs::{self, File, Metadata},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Mode of entries on platforms without Unix permissions
+#[cfg(not(unix))]
+const DEFAULT_MODE: u32 = 0o644;
+
+/// Packs files and directories into `archive`, or standard output if it is `-`.
+pub fn pack(archive: &str, paths: &[String], options: &Options) -> Result<(), Failure> {
+    let mut files = Vec::new();
+    for path in paths {
+        codec::collect_files(Path::new(path), &mut files)
+            .map_err(|e| Failure::Error(format!("{}: {}", path, e)))?;
+    }
+    files.sort();
+
+    let mut writer = ArchiveWriter::new(options.config()).solid(options.solid);
+    for file in &files {
+        let path = entry_path(file)
+            .ok_or_else(|| Failure::Error(format!("{}: invalid entry path", file.display())))?;
+        let data =
+            fs::read(file).map_err(|e| Failure::Error(format!("{}: {}", file.display(), e)))?;
+        let metadata =
+            fs::metadata(file).map_err(|e| Failure::Error(format!("{}: {}", file.display(), e)))?;
+        writer
+            .add(&path, mode(&metadata), mtime(&metadata), &data)
+            .map_err(|e| Failure::Error(format!("{}: {}", file.display(), e)))?;
+        if options.verbose {
+            eprintln!("{}", path);
+        }
+    }
+    let output = writer
+        .finish()
+        .map_err(|e| Failure::Error(format!("compression failed: {}", e)))?;
+
+    if archive == "-" {
+        let mut stdout = io::stdout().lock();
+        return stdout
+            .write_all(&output)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| Failure::Error(format!("standard output: {}", e)));
+    }
+    let path = Path::new(archive);
+    let mut os = codec::create_file(path, options.force)?;
+    os.write_all(&output)
+        .and_then(|_| os.sync_all())
+        .map_err(|e| {
+            let _ = fs::remove_file(path);
+            Failure::Error(format!("{}: {}", archive, e))
+        })
+}
+
+/// Extracts all entries of the archive into the directory of `--directory`, or the current directory.
+pub fn unpack(file: &str, options: &Options) -> Result<(), Failure> {
+    let input = codec::read_input(file)?;
+    let reader = read_archive(&input)?;
+    let contents = reader
+        .read_all()
+        .map_err(|e| Failure::Error(format!("decompression failed: {}", e)))?;
+    let base = options
+        .directory
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
+    for (entry, data) in reader.entries().iter().zip(contents) {
+        let path = entry_destination(&base, &entry.path)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| Failure::Error(format!("{}: {}", parent.display(), e)))?;
+        }
+        let mut os = codec::create_file(&path, options.force)?;
+        os.write_all(&data).map_err(|e| {
+            let _ = fs::remove_file(&path);
+            Failure::Error(format!("{}: {}", path.display(), e))
+        })?;
+        // Setuid, setgid and sticky bits from the archive are not restored
+        set_metadata(&os, entry.mode & 0o777, entry.mtime);
+        if options.verbose {
+            eprintln!("{}", entry.path);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the directory of the archive.
+pub fn list(file: &str, options: &Options) -> Result<(), Failure> {
+    let input = codec::read_input(file)?;
+    let reader = read_archive(&input)?;
+    let entries = reader.entries();
+    let size = entries.iter().map(|v| v.size).sum::<usize>();
+
+    let mut report = String::new();
+    if options.json {
+        report.push_str("{\"file\":");
+        json_string(&mut report, file);
+        let _ = write!(
+            report,
+            ",\"solid\":{},\"size\":{},\"archive_len\":{},\"entries\":[",
+            reader.is_solid(),
+            size,
+            input.len()
+        );
+        for (index, entry) in entries.iter().enumerate() {
+            if index > 0 {
+                report.push(',');
+            }
+            report.push_str("{\"path\":");
+            json_string(&mut report, &entry.path);
+            let _ = write!(
+                report,
+                ",\"size\":{},\"mode\":{},\"mtime\":{},\"crc32\":{},\"compressed_len\":{}}}",
+                entry.size, entry.mode, entry.mtime, entry.crc32, entry.compressed_len
+            );
+        }
+        report.push_str("]}\n");
+    } else {
+        for entry in entries {
+            let compressed = if reader.is_solid() {
+                "-".into()
+            } else {
+                entry.compressed_len.to_string()
+            };
+            let _ = writeln!(
+                report,
+                "{:04o} {:>10} {:>10} {} {:08x} {}",
+                entry.mode,
+                entry.size,
+                compressed,
+                format_time(entry.mtime),
+                entry.crc32,
+                entry.path
+            );
+        }
+        let _ = writeln!(
+            report,
+            "{} files, {} bytes, {} bytes archived{}",
+            entries.len(),
+            size,
+            input.len(),
+            if reader.is_solid() { " (solid)" } else { "" }
+        );
+    }
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(report.as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| Failure::Error(format!("standard output: {}", e)))
+}
+
+fn read_archive(input: &[u8]) -> Result<ArchiveReader<'_>, Failure> {
+    if !input.starts_with(&Archive::SIGNATURE) {
+        return Err(Failure::Error("not an stk1 archive".into()));
+    }
+    ArchiveReader::new(input).map_err(|e| Failure::Error(format!("invalid archive: {}", e)))
+}
+
+/// Returns the path of the entry, without the root and `.` components, or `None` if it has `..`.
+fn entry_path(path: &Path) -> Option<String> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(v) => components.push(v.to_str()?),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    let path = components.join("/");
+    Archive::is_valid_path(&path).then_some(path)
+}
+
+/// Returns the path to extract the entry to.
+///
+/// Fails if the path or any of its directories under `base` is a symbolic link,
+/// which could point outside `base`.
+fn entry_destination(base: &Path, entry: &str) -> Result<PathBuf, Failure> {
+    let mut path = base.to_owned();
+    for component in entry.split('/') {
+        path.push(component);
+        if fs::symlink_metadata(&path).is_ok_and(|v| v.file_type().is_symlink()) {
+            return Err(Failure::Error(format!(
+                "{}: is a symbolic link; not followed",
+                path.display()
+            )));
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &Metadata) -> u32 {
+    DEFAULT_MODE
+}
+
+fn mtime(metadata: &Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|v| v.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |v| v.as_secs())
+}
+
+/// Sets the permissions and modification time of an extracted file, ignoring failures.
+fn set_metadata(file: &File, mode: u32, mtime: u64) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = file.set_permissions(fs::Permissions::from_mode(mode));
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    if let Some(time) = SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(mtime)) {
+        let _ = file.set_modified(time);
+    }
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD hh:mm:ss` in UTC.
+fn format_time(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs = secs % 86400;
+    // Civil date from days since the epoch, by Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,198 @@
+//! bench command
+
+use crate::{codec, counting::CountingAllocator, Failure, Options};
+use libstk1::{Configuration, DecoderKind, Stk1};
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Windows compared by default
+pub const WINDOWS: [(&str, Configuration); 4] = [
+    ("tiny", Configuration::TINY),
+    ("default", Configuration::DEFAULT),
+    ("large", Configuration::LARGE),
+    ("max", Configuration::MAX),
+];
+
+struct Row {
+    window: &'static str,
+    level: u32,
+    files: usize,
+    input_len: usize,
+    compressed_len: usize,
+    /// Times of each run of each file in order
+    encode: Vec<Duration>,
+    decode: Vec<Duration>,
+    encode_memory: usize,
+    decode_memory: usize,
+}
+
+/// Runs the benchmark over all files in `paths`, searching directories recursively.
+pub fn bench(paths: &[String], options: &Options) -> Result<(), Failure> {
+    let mut files = Vec::new();
+    for path in paths {
+        codec::collect_files(Path::new(path), &mut files)
+            .map_err(|e| Failure::Error(format!("{}: {}", path, e)))?;
+    }
+    files.sort();
+    let mut corpus = Vec::new();
+    for file in &files {
+        let data =
+            fs::read(file).map_err(|e| Failure::Error(format!("{}: {}", file.display(), e)))?;
+        corpus.push(data);
+    }
+    let total: usize = corpus.iter().map(|v| v.len()).sum();
+    if total == 0 {
+        return Err(Failure::Error("no input data".into()));
+    }
+    if !options.json {
+        print_header(files.len(), total, options.repeat);
+    }
+
+    for &(window, preset) in WINDOWS
+        .iter()
+        .filter(|(name, _)| options.windows.is_empty() || options.windows.iter().any(|v| v == name))
+    {
+        for &level in &options.levels {
+            let row = run(
+                window,
+                preset.level(level),
+                level,
+                &corpus,
+                options.repeat,
+                options.decoder,
+            )?;
+            print_row(&row, options.json);
+        }
+    }
+    Ok(())
+}
+
+fn print_header(files: usize, total: usize, repeat: usize) {
+    println!("{} files, {} bytes, {} runs each", files, total, repeat);
+    println!(
+        "{:8} {:>5} {:>8} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "window",
+        "level",
+        "ratio",
+        "enc min",
+        "enc med",
+        "dec min",
+        "dec med",
+        "enc mem",
+        "dec mem"
+    );
+}
+
+fn run(
+    window: &'static str,
+    config: Configuration,
+    level: u32,
+    corpus: &[Vec<u8>],
+    repeat: usize,
+    decoder: DecoderKind,
+) -> Result<Row, Failure> {
+    let mut row = Row {
+        window,
+        level,
+        files: corpus.len(),
+        input_len: 0,
+        compressed_len: 0,
+        encode: Vec::new(),
+        decode: Vec::new(),
+        encode_memory: 0,
+        decode_memory: 0,
+    };
+    for input in corpus {
+        row.input_len += input.len();
+
+        let mut compressed = Vec::new();
+        for _ in 0..repeat {
+            drop(compressed);
+            let base = CountingAllocator::reset_peak();
+            let start = Instant::now();
+            compressed = Stk1::encode(input, config)
+                .map_err(|e| Failure::Error(format!("compression failed: {}", e)))?;
+            row.encode.push(start.elapsed());
+            row.encode_memory = row.encode_memory.max(CountingAllocator::peak() - base);
+        }
+        row.compressed_len += compressed.len();
+
+        // The output buffer is counted in the decoding memory but not in the time
+        let mut decoded = vec![0; input.len()];
+        for _ in 0..repeat {
+            let base = CountingAllocator::reset_peak();
+            let start = Instant::now();
+            Stk1::decode_with(decoder, &compressed, &mut decoded)
+                .map_err(|e| Failure::Error(format!("decompression failed: {}", e)))?;
+            row.decode.push(start.elapsed());
+            row.decode_memory = row
+                .decode_memory
+                .max(CountingAllocator::peak() - base + input.len());
+            if decoded != *input {
+                return Err(Failure::Error(format!(
+                    "decoded data mismatch at window {} level {}",
+                    window, level
+                )));
+            }
+        }
+    }
+    Ok(row)
+}
+
+/// Returns the minimum and median throughput in MB/s of runs over the corpus.
+///
+/// The times of the same run of each file are summed.
+fn throughput(times: &[Duration], files: usize, bytes: usize) -> (f64, f64) {
+    let repeat = times.len() / files;
+    let mut totals = (0..repeat)
+        .map(|run| {
+            (0..files)
+                .map(|file| times[file * repeat + run])
+                .sum::<Duration>()
+                .as_secs_f64()
+        })
+        .collect::<Vec<_>>();
+    totals.sort_by(f64::total_cmp);
+    let mbps = |secs: f64| bytes as f64 / 1_000_000.0 / secs.max(1e-9);
+    (
+        mbps(totals[totals.len() - 1]),
+        mbps(totals[totals.len() / 2]),
+    )
+}
+
+/// Prints a row of the table, or a line of JSON.
+fn print_row(row: &Row, json: bool) {
+    let (enc_min, enc_med) = throughput(&row.encode, row.files, row.input_len);
+    let (dec_min, dec_med) = throughput(&row.decode, row.files, row.input_len);
+    if json {
+        println!(
+            "{{\"window\":\"{}\",\"level\":{},\"input_len\":{},\"compressed_len\":{},\"encode_mbps\":[{:.3},{:.3}],\"decode_mbps\":[{:.3},{:.3}],\"encode_memory\":{},\"decode_memory\":{}}}",
+            row.window,
+            row.level,
+            row.input_len,
+            row.compressed_len,
+            enc_min,
+            enc_med,
+            dec_min,
+            dec_med,
+            row.encode_memory,
+            row.decode_memory
+        );
+        return;
+    }
+    println!(
+        "{:8} {:>5} {:>7.2}% {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>9.1}M {:>9.1}M",
+        row.window,
+        row.level,
+        row.compressed_len as f64 / row.input_len as f64 * 100.0,
+        enc_min,
+        enc_med,
+        dec_min,
+        dec_med,
+        row.encode_memory as f64 / (1 << 20) as f64,
+        row.decode_memory as f64 / (1 << 20) as f64,
+    );
+}
@@ -0,0 +1,198 @@
+//! compress, decompress and test commands
+
+use crate::{Failure, Options};
+use libstk1::{Stk1, Tek1};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Name of standard input and output in the file list
+const STDIO: &str = "-";
+
+pub fn compress(file: &str, options: &Options) -> Result<(), Failure> {
+    let to_stdout = options.stdout || file == STDIO;
+    if to_stdout && !options.force && io::stdout().is_terminal() {
+        return Err(Failure::Error(
+            "compressed data not written to a terminal; use --force to force it".into(),
+        ));
+    }
+    if file != STDIO && !to_stdout && file.ends_with(&options.suffix) {
+        return Err(Failure::Warning(format!(
+            "already has {} suffix -- unchanged",
+            options.suffix
+        )));
+    }
+
+    let input = read_input(file)?;
+    if !options.raw && input.len() > Tek1::MAX_SIZE {
+        return Err(Failure::Error(format!(
+            "{} bytes exceeds the tek1 limit of {} bytes; use --raw to compress without the header",
+            input.len(),
+            Tek1::MAX_SIZE
+        )));
+    }
+    let output = if options.raw {
+        Stk1::encode(&input, options.config())
+    } else {
+        Tek1::encode(&input, options.config())
+    }
+    .map_err(|e| Failure::Error(format!("compression failed: {}", e)))?;
+
+    if options.verbose {
+        eprintln!(
+            "{}: {} => {} bytes ({:.2}%)",
+            file,
+            input.len(),
+            output.len(),
+            ratio(output.len(), input.len())
+        );
+    }
+    let output_path = (!to_stdout).then(|| PathBuf::from(format!("{}{}", file, options.suffix)));
+    write_output(file, output_path.as_deref(), &output, options)
+}
+
+pub fn decompress(file: &str, options: &Options) -> Result<(), Failure> {
+    let to_stdout = options.stdout || file == STDIO;
+    let output_path = if to_stdout {
+        None
+    } else {
+        match file.strip_suffix(&options.suffix) {
+            Some(stem) if !stem.is_empty() => Some(PathBuf::from(stem)),
+            _ => {
+                return Err(Failure::Warning(format!(
+                    "unknown suffix, expected {} -- ignored",
+                    options.suffix
+                )))
+            }
+        }
+    };
+
+    let input = read_input(file)?;
+    let output = decode(&input, options)?;
+    if options.verbose {
+        eprintln!(
+            "{}: {} => {} bytes ({:.2}%)",
+            file,
+            input.len(),
+            output.len(),
+            ratio(input.len(), output.len())
+        );
+    }
+    write_output(file, output_path.as_deref(), &output, options)
+}
+
+pub fn test(file: &str, options: &Options) -> Result<(), Failure> {
+    let input = read_input(file)?;
+    let output = decode(&input, options)?;
+    if options.verbose {
+        eprintln!("{}: OK ({} bytes)", file, output.len());
+    }
+    Ok(())
+}
+
+fn decode(input: &[u8], options: &Options) -> Result<Vec<u8>, Failure> {
+    if options.raw {
+        let size = options
+            .size
+            .ok_or_else(|| Failure::Error("--size is required to decompress raw data".into()))?;
+        Stk1::decode_to_vec(input, size)
+    } else {
+        if !input.starts_with(&Tek1::SIGNATURE) {
+            return Err(Failure::Error("not in tek1 format".into()));
+        }
+        Tek1::decode_to_vec(input)
+    }
+    .map_err(|e| Failure::Error(format!("decompression failed: {}", e)))
+}
+
+#[inline]
+fn ratio(compressed: usize, decompressed: usize) -> f64 {
+    if decompressed == 0 {
+        0.0
+    } else {
+        compressed as f64 / decompressed as f64 * 100.0
+    }
+}
+
+pub fn read_input(file: &str) -> Result<Vec<u8>, Failure> {
+    let mut input = Vec::new();
+    let result = if file == STDIO {
+        io::stdin().lock().read_to_end(&mut input)
+    } else {
+        File::open(file).and_then(|mut v| v.read_to_end(&mut input))
+    };
+    result.map_err(|e| Failure::Error(e.to_string()))?;
+    Ok(input)
+}
+
+/// Writes the result to `output_path`, or to standard output if it is `None`,
+/// then removes the input file unless it is kept.
+fn write_output(
+    file: &str,
+    output_path: Option<&Path>,
+    data: &[u8],
+    options: &Options,
+) -> Result<(), Failure> {
+    let Some(output_path) = output_path else {
+        let mut stdout = io::stdout().lock();
+        return stdout
+            .write_all(data)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| Failure::Error(format!("standard output: {}", e)));
+    };
+
+    let mut os = create_file(output_path, options.force)?;
+    if let Err(e) = os.write_all(data).and_then(|_| os.sync_all()) {
+        drop(os);
+        let _ = fs::remove_file(output_path);
+        return Err(Failure::Error(format!("{}: {}", output_path.display(), e)));
+    }
+    if let Ok(metadata) = fs::metadata(file) {
+        let _ = fs::set_permissions(output_path, metadata.permissions());
+    }
+
+    if !options.keep {
+        fs::remove_file(file).map_err(|e| Failure::Error(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Creates the output file, which must not exist unless `force` is set.
+pub fn create_file(path: &Path, force: bool) -> Result<File, Failure> {
+    let mut open = OpenOptions::new();
+    open.write(true);
+    if force {
+        open.create(true).truncate(true);
+    } else {
+        open.create_new(true);
+    }
+    open.open(path).map_err(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            Failure::Error(format!(
+                "{} already exists; use --force to overwrite",
+                path.display()
+            ))
+        } else {
+            Failure::Error(format!("{}: {}", path.display(), e))
+        }
+    })
+}
+
+/// Appends `path` to `files`, or all files under it if it is a directory.
+pub fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            // Symbolic links in directories are skipped, as they can form a loop
+            if !fs::symlink_metadata(&path)?.file_type().is_symlink() {
+                collect_files(&path, files)?;
+            }
+        }
+    } else {
+        fs::metadata(path)?;
+        files.push(path.to_owned());
+    }
+    Ok(())
+}
@@ -0,0 +1,302 @@
+//! inspect command
+
+use crate::{codec, Failure, Options};
+use libstk1::{Stk1, Tek1, Tek1Header, Token, TokenKind};
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+/// Number of literal bytes shown in a line of the listing
+const PREVIEW_LEN: usize = 16;
+
+#[derive(Debug, Default)]
+struct Summary {
+    groups: usize,
+    literal_runs: usize,
+    literal_bytes: usize,
+    matches: usize,
+    match_bytes: usize,
+}
+
+impl Summary {
+    fn add(&mut self, token: &Token) {
+        match token.kind {
+            TokenKind::Group { .. } => self.groups += 1,
+            TokenKind::Literals(literals) => {
+                self.literal_runs += 1;
+                self.literal_bytes += literals.len();
+            }
+            TokenKind::Match { .. } => {
+                self.matches += 1;
+                self.match_bytes += token.decoded_len();
+            }
+        }
+    }
+
+    #[inline]
+    fn tokens(&self) -> usize {
+        self.groups + self.literal_runs + self.matches
+    }
+}
+
+pub fn inspect(file: &str, options: &Options) -> Result<(), Failure> {
+    let input = codec::read_input(file)?;
+    let (header, body) = if input.starts_with(&Tek1::SIGNATURE) {
+        let header = Tek1::parse_header(&input)
+            .map_err(|e| Failure::Error(format!("invalid tek1 header: {}", e)))?;
+        (Some(header), &input[header.len..])
+    } else {
+        (None, &input[..])
+    };
+    let size = header.map(|v| v.size).or(options.size);
+
+    let mut summary = Summary::default();
+    let mut listing = String::new();
+    let mut tokens = Stk1::tokens(body, size.unwrap_or(usize::MAX));
+    let base = header.map(|v| v.len).unwrap_or(0);
+    let mut error = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            Ok(token) => {
+                summary.add(&token);
+                if !options.summary {
+                    if options.json {
+                        json_token(&mut listing, &token, base, summary.tokens() == 1);
+                    } else {
+                        text_token(&mut listing, &token, base);
+                    }
+                }
+            }
+            Err(e) => error = Some((base + tokens.offset(), e)),
+        }
+    }
+    let decoded = tokens.position();
+    let trailing = if error.is_none() {
+        body.len() - tokens.offset()
+    } else {
+        0
+    };
+
+    let mut report = String::new();
+    if options.json {
+        json_report(&mut report, file, header, size, decoded, trailing, &summary);
+        let _ = write!(report, ",\"tokens\":[{}]", listing);
+        match &error {
+            Some((offset, e)) => {
+                let _ = write!(
+                    report,
+                    ",\"error\":{{\"offset\":{},\"message\":\"{}\"}}}}",
+                    offset, e
+                );
+            }
+            None => report.push_str(",\"error\":null}"),
+        }
+        report.push('\n');
+    } else {
+        text_report(&mut report, file, header, size, decoded, trailing, &summary);
+        if !options.summary {
+            report.push_str("offset   bytes output             token\n");
+            report.push_str(&listing);
+        }
+    }
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(report.as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| Failure::Error(format!("standard output: {}", e)))?;
+
+    match error {
+        Some((offset, e)) => Err(Failure::Error(format!("{} at offset {:#x}", e, offset))),
+        None => Ok(()),
+    }
+}
+
+fn text_report(
+    report: &mut String,
+    file: &str,
+    header: Option<Tek1Header>,
+    size: Option<usize>,
+    decoded: usize,
+    trailing: usize,
+    summary: &Summary,
+) {
+    let _ = writeln!(report, "file: {}", file);
+    match header {
+        Some(header) => {
+            let _ = writeln!(report, "format: tek1");
+            let _ = writeln!(report, "size: {}", header.size);
+            let _ = writeln!(
+                report,
+                "bsiz: {} ({} bytes)",
+                header.bsiz,
+                1usize << (header.bsiz + 8)
+            );
+            let _ = writeln!(report, "md: {}", header.md);
+            let _ = writeln!(report, "mds: {}", header.mds);
+            if let Some(extra) = header.extra {
+                let _ = writeln!(report, "extra: {}", extra);
+            }
+            let _ = writeln!(report, "header: {} bytes", header.len);
+        }
+        None => {
+            let _ = writeln!(report, "format: stk1 (no header)");
+            match size {
+                Some(size) => {
+                    let _ = writeln!(report, "size: {}", size);
+                }
+                None => {
+                    let _ = writeln!(report, "size: unknown");
+                }
+            }
+        }
+    }
+    let _ = writeln!(report, "decoded: {} bytes", decoded);
+    if trailing > 0 {
+        let _ = writeln!(report, "trailing: {} bytes", trailing);
+    }
+    let _ = writeln!(
+        report,
+        "tokens: {} ({} groups, {} literal runs of {} bytes, {} matches of {} bytes)",
+        summary.tokens(),
+        summary.groups,
+        summary.literal_runs,
+        summary.literal_bytes,
+        summary.matches,
+        summary.match_bytes
+    );
+}
+
+fn text_token(listing: &mut String, token: &Token, base: usize) {
+    let _ = write!(
+        listing,
+        "{:08x} {:5} ",
+        base + token.offset,
+        token.encoded_len
+    );
+    if token.decoded_len() > 0 {
+        let _ = write!(
+            listing,
+            "{:08x}..{:08x} ",
+            token.position,
+            token.position + token.decoded_len()
+        );
+    } else {
+        let _ = write!(listing, "{:08x}           ", token.position);
+    }
+    match token.kind {
+        TokenKind::Group { literals, matches } => {
+            let _ = writeln!(listing, "group literals={} matches={}", literals, matches);
+        }
+        TokenKind::Literals(literals) => {
+            let _ = write!(listing, "literals \"");
+            for &byte in literals.iter().take(PREVIEW_LEN) {
+                let _ = write!(listing, "{}", byte.escape_ascii());
+            }
+            let _ = writeln!(
+                listing,
+                "\"{}",
+                if literals.len() > PREVIEW_LEN {
+                    "..."
+                } else {
+                    ""
+                }
+            );
+        }
+        TokenKind::Match { distance, len } => {
+            let _ = writeln!(listing, "match distance={} len={}", distance, len);
+        }
+    }
+}
+
+fn json_report(
+    report: &mut String,
+    file: &str,
+    header: Option<Tek1Header>,
+    size: Option<usize>,
+    decoded: usize,
+    trailing: usize,
+    summary: &Summary,
+) {
+    let _ = write!(report, "{{\"file\":");
+    json_string(report, file);
+    match header {
+        Some(header) => {
+            let _ = write!(
+                report,
+                ",\"format\":\"tek1\",\"header\":{{\"size\":{},\"bsiz\":{},\"md\":{},\"mds\":{},\"extra\":{},\"len\":{}}}",
+                header.size,
+                header.bsiz,
+                header.md,
+                header.mds,
+                header.extra.map(|v| v.to_string()).unwrap_or("null".into()),
+                header.len
+            );
+        }
+        None => report.push_str(",\"format\":\"stk1\",\"header\":null"),
+    }
+    let _ = write!(
+        report,
+        ",\"size\":{},\"decoded\":{},\"trailing\":{},\"groups\":{},\"literal_runs\":{},\"literal_bytes\":{},\"matches\":{},\"match_bytes\":{}",
+        size.map(|v| v.to_string()).unwrap_or("null".into()),
+        decoded,
+        trailing,
+        summary.groups,
+        summary.literal_runs,
+        summary.literal_bytes,
+        summary.matches,
+        summary.match_bytes
+    );
+}
+
+fn json_token(listing: &mut String, token: &Token, base: usize, is_first: bool) {
+    if !is_first {
+        listing.push(',');
+    }
+    let _ = write!(
+        listing,
+        "{{\"offset\":{},\"bytes\":{},\"output\":[{},{}],",
+        base + token.offset,
+        token.encoded_len,
+        token.position,
+        token.position + token.decoded_len()
+    );
+    match token.kind {
+        TokenKind::Group { literals, matches } => {
+            let _ = write!(
+                listing,
+                "\"type\":\"group\",\"literals\":{},\"matches\":{}}}",
+                literals, matches
+            );
+        }
+        TokenKind::Literals(literals) => {
+            listing.push_str("\"type\":\"literals\",\"data\":\"");
+            for byte in literals {
+                let _ = write!(listing, "{:02x}", byte);
+            }
+            listing.push_str("\"}");
+        }
+        TokenKind::Match { distance, len } => {
+            let _ = write!(
+                listing,
+                "\"type\":\"match\",\"distance\":{},\"len\":{}}}",
+                distance, len
+            );
+        }
+    }
+}
+
+pub fn json_string(output: &mut String, value: &str) {
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(output, "\\u{:04x}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
@@ -0,0 +1,409 @@
+//! Command-line tool for stk1 and tek1 files.
+
+mod archive;
+mod bench;
+mod codec;
+mod counting;
+mod inspect;
+mod sfx;
+mod visualize;
+
+use counting::CountingAllocator;
+use libstk1::{Configuration, DecoderKind};
+use std::{env, fmt, path::PathBuf, process};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const USAGE: &str = "\
+Usage: stk1 <COMMAND> [OPTIONS] [FILES]...
+
+Commands:
+  compress, c      Compress files up to 8MB (FILE to FILE.tek)
+  decompress, d    Decompress files (FILE.tek to FILE)
+  test, t          Test the integrity of compressed files
+  inspect, i       Print the header and tokens of compressed files
+  bench            Measure speed, ratio and memory of every window and level
+                   over files and directories
+  pack             Pack files and directories into an archive (ARCHIVE FILES...)
+  unpack           Extract all files of archives
+  list             List the files of archives
+  sfx              Make self-extracting x86-64 Linux executables (FILE to FILE.sfx)
+  visualize, v     Print files as a hexdump colored by how each byte is encoded
+  help             Print this help
+
+Options:
+  -c, --stdout         Write to standard output and keep input files
+  -k, --keep           Keep input files
+  -f, --force          Overwrite existing output files
+  -1 .. -9             Compression level (default: 6)
+      --level <N>      Same as -N
+      --window <W>     Window size: tiny, default or large
+      --raw            Read and write stk1 data without the tek1 header
+                       and its limit of 8MB per file
+      --size <BYTES>   Decompressed size of raw data
+  -S, --suffix <SUF>   Suffix of compressed files (default: .tek)
+  -q, --quiet          Suppress warnings
+  -v, --verbose        Print the name and ratio of each file
+      --json           Print the result of inspect, list or bench in JSON
+      --summary        Print only the summary of inspect
+      --html           Print the result of visualize as an HTML page
+      --solid          Compress all files of pack as one stream
+      --exec           Make sfx executables run the file instead of printing it
+  -C, --directory <DIR>
+                       Directory to unpack into (default: current directory)
+      --repeat <N>     Number of runs of bench (default: 3)
+      --levels <L>     Levels of bench, such as 1-9 or 1,6,9 (default: 1-9)
+      --windows <W>    Windows of bench among tiny,default,large,max (default: all)
+      --decoder <D>    Decoder of bench: rust or x86_64 (default: rust)
+
+With no FILES, or when FILE is -, read standard input and write standard output.
+
+Exit status: 0 on success, 1 on errors, 2 on warnings only.";
+
+/// Exit status of the tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success = 0,
+    Error = 1,
+    Warning = 2,
+}
+
+impl Status {
+    /// Returns the more severe status of the two.
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Error, _) | (_, Self::Error) => Self::Error,
+            (Self::Warning, _) | (_, Self::Warning) => Self::Warning,
+            _ => Self::Success,
+        }
+    }
+}
+
+/// Failure of a command on a file
+#[derive(Debug)]
+pub enum Failure {
+    /// The file was skipped
+    Warning(String),
+    Error(String),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning(message) | Self::Error(message) => f.write_str(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub stdout: bool,
+    pub keep: bool,
+    pub force: bool,
+    pub level: u32,
+    pub window: Configuration,
+    pub raw: bool,
+    pub size: Option<usize>,
+    pub suffix: String,
+    pub quiet: bool,
+    pub verbose: bool,
+    pub json: bool,
+    pub summary: bool,
+    pub html: bool,
+    pub solid: bool,
+    pub exec: bool,
+    pub directory: Option<PathBuf>,
+    pub repeat: usize,
+    pub levels: Vec<u32>,
+    pub windows: Vec<String>,
+    pub decoder: DecoderKind,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            stdout: false,
+            keep: false,
+            force: false,
+            level: 6,
+            window: Configuration::DEFAULT,
+            raw: false,
+            size: None,
+            suffix: ".tek".into(),
+            quiet: false,
+            verbose: false,
+            json: false,
+            summary: false,
+            html: false,
+            solid: false,
+            exec: false,
+            directory: None,
+            repeat: 3,
+            levels: (1..=9).collect(),
+            windows: Vec::new(),
+            decoder: DecoderKind::Rust,
+        }
+    }
+}
+
+impl Options {
+    #[inline]
+    pub fn config(&self) -> Configuration {
+        self.window.level(self.level)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Compress,
+    Decompress,
+    Test,
+    Inspect,
+    Bench,
+    Visualize,
+    Pack,
+    Unpack,
+    List,
+    Sfx,
+    Help,
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("stk1: {}", message);
+    eprintln!("Try 'stk1 help' for more information.");
+    process::exit(Status::Error as i32);
+}
+
+/// Returns the value of an option, from `--name=value` or the next argument.
+fn option_value(
+    name: &str,
+    inline: Option<&str>,
+    args: &mut impl Iterator<Item = String>,
+) -> String {
+    match inline {
+        Some(v) => v.to_owned(),
+        None => args
+            .next()
+            .unwrap_or_else(|| usage_error(&format!("option '{}' requires a value", name))),
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> (Command, Options, Vec<String>) {
+    let command = match args.next().as_deref() {
+        Some("compress" | "c") => Command::Compress,
+        Some("decompress" | "d") => Command::Decompress,
+        Some("test" | "t") => Command::Test,
+        Some("inspect" | "i") => Command::Inspect,
+        Some("bench") => Command::Bench,
+        Some("visualize" | "v") => Command::Visualize,
+        Some("pack") => Command::Pack,
+        Some("unpack") => Command::Unpack,
+        Some("list") => Command::List,
+        Some("sfx") => Command::Sfx,
+        Some("help" | "-h" | "--help") => Command::Help,
+        Some(other) => usage_error(&format!("unknown command '{}'", other)),
+        None => usage_error("no command given"),
+    };
+
+    let mut options = Options::default();
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            files.extend(args.by_ref());
+            break;
+        } else if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            match name {
+                "stdout" => options.stdout = true,
+                "keep" => options.keep = true,
+                "force" => options.force = true,
+                "raw" => options.raw = true,
+                "quiet" => options.quiet = true,
+                "verbose" => options.verbose = true,
+                "json" => options.json = true,
+                "summary" => options.summary = true,
+                "html" => options.html = true,
+                "solid" => options.solid = true,
+                "exec" => options.exec = true,
+                "directory" => {
+                    options.directory = Some(option_value(name, inline, &mut args).into());
+                }
+                "help" => return (Command::Help, options, files),
+                "level" => {
+                    let value = option_value(name, inline, &mut args);
+                    options.level = match value.parse() {
+                        Ok(v @ 1..=9) => v,
+                        _ => usage_error(&format!("invalid level '{}'", value)),
+                    };
+                }
+                "window" => {
+                    options.window = match option_value(name, inline, &mut args).as_str() {
+                        "tiny" => Configuration::TINY,
+                        "default" => Configuration::DEFAULT,
+                        "large" => Configuration::LARGE,
+                        other => usage_error(&format!("invalid window '{}'", other)),
+                    };
+                }
+                "size" => {
+                    let value = option_value(name, inline, &mut args);
+                    options.size = Some(
+                        value
+                            .parse()
+                            .unwrap_or_else(|_| usage_error(&format!("invalid size '{}'", value))),
+                    );
+                }
+                "suffix" => options.suffix = option_value(name, inline, &mut args),
+                "repeat" => {
+                    let value = option_value(name, inline, &mut args);
+                    options.repeat = match value.parse() {
+                        Ok(v @ 1..) => v,
+                        _ => usage_error(&format!("invalid repeat count '{}'", value)),
+                    };
+                }
+                "levels" => {
+                    let value = option_value(name, inline, &mut args);
+                    options.levels = parse_levels(&value)
+                        .unwrap_or_else(|| usage_error(&format!("invalid levels '{}'", value)));
+                }
+                "windows" => {
+                    let value = option_value(name, inline, &mut args);
+                    options.windows = value.split(',').map(String::from).collect();
+                    for window in &options.windows {
+                        if !bench::WINDOWS.iter().any(|(name, _)| name == window) {
+                            usage_error(&format!("invalid window '{}'", window));
+                        }
+                    }
+                }
+                "decoder" => {
+                    options.decoder = match option_value(name, inline, &mut args).as_str() {
+                        "rust" => DecoderKind::Rust,
+                        #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+                        "x86_64" => DecoderKind::X86_64,
+                        other => usage_error(&format!("unsupported decoder '{}'", other)),
+                    };
+                }
+                _ => usage_error(&format!("unknown option '{}'", arg)),
+            }
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            let mut chars = arg[1..].chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    'c' => options.stdout = true,
+                    'k' => options.keep = true,
+                    'f' => options.force = true,
+                    'q' => options.quiet = true,
+                    'v' => options.verbose = true,
+                    'h' => return (Command::Help, options, files),
+                    '1'..='9' => options.level = c as u32 - '0' as u32,
+                    'S' => {
+                        let rest = chars.as_str();
+                        options.suffix =
+                            option_value("-S", (!rest.is_empty()).then_some(rest), &mut args);
+                        break;
+                    }
+                    'C' => {
+                        let rest = chars.as_str();
+                        options.directory = Some(
+                            option_value("-C", (!rest.is_empty()).then_some(rest), &mut args)
+                                .into(),
+                        );
+                        break;
+                    }
+                    _ => usage_error(&format!("unknown option '-{}'", c)),
+                }
+            }
+        } else {
+            files.push(arg);
+        }
+    }
+    if options.suffix.is_empty() {
+        usage_error("suffix must not be empty");
+    }
+    (command, options, files)
+}
+
+/// Parses a list of levels such as `1-3,6,9`.
+fn parse_levels(value: &str) -> Option<Vec<u32>> {
+    let mut levels = Vec::new();
+    for item in value.split(',') {
+        let (first, last) = match item.split_once('-') {
+            Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+            None => {
+                let level = item.parse().ok()?;
+                (level, level)
+            }
+        };
+        if !(1..=9).contains(&first) || !(first..=9).contains(&last) {
+            return None;
+        }
+        levels.extend(first..=last);
+    }
+    Some(levels)
+}
+
+fn main() {
+    let (command, options, mut files) = parse_args(env::args().skip(1));
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            return;
+        }
+        Command::Bench => {
+            if files.is_empty() {
+                usage_error("bench requires files or directories");
+            }
+            if let Err(failure) = bench::bench(&files, &options) {
+                eprintln!("stk1: {}", failure);
+                process::exit(Status::Error as i32);
+            }
+            return;
+        }
+        Command::Pack => {
+            if files.len() < 2 {
+                usage_error("pack requires an archive and files or directories");
+            }
+            if let Err(failure) = archive::pack(&files[0], &files[1..], &options) {
+                eprintln!("stk1: {}: {}", files[0], failure);
+                process::exit(Status::Error as i32);
+            }
+            return;
+        }
+        _ => {}
+    }
+    if files.is_empty() {
+        files.push("-".into());
+    }
+
+    let mut status = Status::Success;
+    for file in &files {
+        let result = match command {
+            Command::Compress => codec::compress(file, &options),
+            Command::Decompress => codec::decompress(file, &options),
+            Command::Test => codec::test(file, &options),
+            Command::Inspect => inspect::inspect(file, &options),
+            Command::Visualize => visualize::visualize(file, &options),
+            Command::Unpack => archive::unpack(file, &options),
+            Command::List => archive::list(file, &options),
+            Command::Sfx => sfx::sfx(file, &options),
+            Command::Bench | Command::Pack | Command::Help => unreachable!(),
+        };
+        match result {
+            Ok(()) => {}
+            Err(Failure::Warning(message)) => {
+                if !options.quiet {
+                    eprintln!("stk1: {}: {}", file, message);
+                }
+                status = status.merge(Status::Warning);
+            }
+            Err(Failure::Error(message)) => {
+                eprintln!("stk1: {}: {}", file, message);
+                status = status.merge(Status::Error);
+            }
+        }
+    }
+    process::exit(status as i32);
+}
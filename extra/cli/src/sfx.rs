@@ -0,0 +1,56 @@
+//! sfx command
+
+use crate::{codec, Failure, Options};
+use libstk1::{Sfx, SfxMode};
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::Path,
+};
+
+/// Suffix of generated executables
+const SUFFIX: &str = ".sfx";
+
+/// Writes a self-extracting executable of `file` to `FILE.sfx`, or standard output.
+pub fn sfx(file: &str, options: &Options) -> Result<(), Failure> {
+    let to_stdout = options.stdout || file == "-";
+    if to_stdout && !options.force && io::stdout().is_terminal() {
+        return Err(Failure::Error(
+            "executable not written to a terminal; use --force to force it".into(),
+        ));
+    }
+    let input = codec::read_input(file)?;
+    let mode = if options.exec {
+        SfxMode::Exec
+    } else {
+        SfxMode::Write
+    };
+    let output = Sfx::build(&input, options.config(), mode)
+        .map_err(|e| Failure::Error(format!("compression failed: {}", e)))?;
+    if options.verbose {
+        eprintln!("{}: {} => {} bytes", file, input.len(), output.len());
+    }
+
+    if to_stdout {
+        let mut stdout = io::stdout().lock();
+        return stdout
+            .write_all(&output)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| Failure::Error(format!("standard output: {}", e)));
+    }
+    let path = format!("{}{}", file, SUFFIX);
+    let path = Path::new(&path);
+    let mut os = codec::create_file(path, options.force)?;
+    if let Err(e) = os.write_all(&output).and_then(|_| os.sync_all()) {
+        drop(os);
+        let _ = fs::remove_file(path);
+        return Err(Failure::Error(format!("{}: {}", path.display(), e)));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| Failure::Error(format!("{}: {}", path.display(), e)))?;
+    }
+    Ok(())
+}
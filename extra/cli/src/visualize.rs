@@ -0,0 +1,274 @@
+//! visualize command
+
+use crate::{codec, Failure, Options};
+use libstk1::{Stk1, TokenKind};
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+const BYTES_PER_LINE: usize = 16;
+
+/// How bytes were encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Literal,
+    /// Match whose distance fits in the leading byte
+    Short,
+    /// Match with a 2-byte distance
+    Near,
+    /// Match with a distance of 3 bytes or more
+    Far,
+}
+
+impl Class {
+    const ALL: [Self; 4] = [Self::Literal, Self::Short, Self::Near, Self::Far];
+
+    /// Returns the class of a match by the number of bytes of the distance,
+    /// which has 3 bits in the leading byte and 7 bits in each following byte.
+    fn of_match(distance: usize) -> Self {
+        match (distance - 1) >> 3 {
+            0 => Self::Short,
+            1..=0x7F => Self::Near,
+            _ => Self::Far,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Literal => "literal",
+            Self::Short => "match, 1-byte distance",
+            Self::Near => "match, 2-byte distance",
+            Self::Far => "match, 3+ byte distance",
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Self::Literal => "\x1b[31m",
+            Self::Short => "\x1b[32m",
+            Self::Near => "\x1b[36m",
+            Self::Far => "\x1b[34m",
+        }
+    }
+
+    fn css_class(self) -> &'static str {
+        match self {
+            Self::Literal => "l",
+            Self::Short => "s",
+            Self::Near => "n",
+            Self::Far => "f",
+        }
+    }
+}
+
+/// Bytes of the input produced by a token
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    class: Class,
+    start: usize,
+    len: usize,
+    distance: usize,
+}
+
+impl Span {
+    #[inline]
+    fn end(&self) -> usize {
+        self.start + self.len
+    }
+
+    fn tooltip(&self) -> String {
+        match self.class {
+            Class::Literal => format!("literals {:#x}..{:#x}", self.start, self.end()),
+            _ => format!(
+                "match from {:#x} (distance {}, length {})",
+                self.start - self.distance,
+                self.distance,
+                self.len
+            ),
+        }
+    }
+}
+
+pub fn visualize(file: &str, options: &Options) -> Result<(), Failure> {
+    let input = codec::read_input(file)?;
+    let encoded = Stk1::encode(&input, options.config())
+        .map_err(|e| Failure::Error(format!("compression failed: {}", e)))?;
+    let mut spans = Vec::new();
+    for token in Stk1::tokens(&encoded, input.len()) {
+        let token = token.map_err(|e| Failure::Error(format!("invalid token stream: {}", e)))?;
+        let (class, distance) = match token.kind {
+            TokenKind::Group { .. } => continue,
+            TokenKind::Literals(_) => (Class::Literal, 0),
+            TokenKind::Match { distance, .. } => (Class::of_match(distance), distance),
+        };
+        spans.push(Span {
+            class,
+            start: token.position,
+            len: token.decoded_len(),
+            distance,
+        });
+    }
+
+    let report = if options.html {
+        html(file, &input, &spans, encoded.len())
+    } else {
+        ansi(file, &input, &spans, encoded.len())
+    };
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(report.as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| Failure::Error(format!("standard output: {}", e)))
+}
+
+/// Returns the number of bytes of each class.
+fn totals(spans: &[Span]) -> [usize; 4] {
+    let mut totals = [0; 4];
+    for span in spans {
+        totals[Class::ALL.iter().position(|&v| v == span.class).unwrap()] += span.len;
+    }
+    totals
+}
+
+/// Renders the hexdump with 16 bytes per line, wrapping the bytes of each span
+/// in the line between `open(span)` and `close`.
+fn hexdump(
+    output: &mut String,
+    input: &[u8],
+    spans: &[Span],
+    open: impl Fn(&Span) -> String,
+    close: &str,
+    escape: impl Fn(&mut String, char),
+) {
+    let mut index = 0;
+    let mut text = String::new();
+    for line_start in (0..input.len()).step_by(BYTES_PER_LINE) {
+        let line_end = (line_start + BYTES_PER_LINE).min(input.len());
+        let _ = write!(output, "{:08x} ", line_start);
+        text.clear();
+        let mut cursor = line_start;
+        while cursor < line_end {
+            while spans[index].end() <= cursor {
+                index += 1;
+            }
+            let span = &spans[index];
+            let end = span.end().min(line_end);
+            let tag = open(span);
+            output.push_str(&tag);
+            text.push_str(&tag);
+            for &byte in &input[cursor..end] {
+                let _ = write!(output, " {:02x}", byte);
+                escape(&mut text, ascii(byte));
+            }
+            output.push_str(close);
+            text.push_str(close);
+            cursor = end;
+        }
+        let padding = BYTES_PER_LINE - (line_end - line_start);
+        let _ = writeln!(output, "{} |{}|", "   ".repeat(padding), text);
+    }
+}
+
+fn ascii(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+fn ansi(file: &str, input: &[u8], spans: &[Span], compressed_len: usize) -> String {
+    const RESET: &str = "\x1b[0m";
+    let mut output = String::new();
+    let _ = writeln!(
+        output,
+        "{}: {} => {} bytes",
+        file,
+        input.len(),
+        compressed_len
+    );
+    for (class, total) in Class::ALL.iter().zip(totals(spans)) {
+        let _ = writeln!(
+            output,
+            "{}##{} {:24} {:>10} bytes",
+            class.ansi(),
+            RESET,
+            class.name(),
+            total
+        );
+    }
+
+    hexdump(
+        &mut output,
+        input,
+        spans,
+        |span| span.class.ansi().into(),
+        RESET,
+        String::push,
+    );
+    output
+}
+
+fn html_escape(output: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn html(file: &str, input: &[u8], spans: &[Span], compressed_len: usize) -> String {
+    let mut output = String::new();
+    output.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    html_escape(&mut output, file);
+    output.push_str(
+        "</title>\n<style>\n\
+         body { font-family: sans-serif; }\n\
+         pre { font-family: monospace; line-height: 1.3; }\n\
+         .l { color: #c00; }\n\
+         .s { color: #080; }\n\
+         .n { color: #088; }\n\
+         .f { color: #00c; }\n\
+         pre span:hover { background: #ff8; }\n\
+         </style>\n</head>\n<body>\n<h1>",
+    );
+    html_escape(&mut output, file);
+    let _ = writeln!(
+        output,
+        "</h1>\n<p>{} bytes compressed to {} bytes</p>\n<ul>",
+        input.len(),
+        compressed_len
+    );
+    for (class, total) in Class::ALL.iter().zip(totals(spans)) {
+        let _ = writeln!(
+            output,
+            "<li class=\"{}\">{}: {} bytes</li>",
+            class.css_class(),
+            class.name(),
+            total
+        );
+    }
+    output.push_str("</ul>\n<pre>\n");
+
+    hexdump(
+        &mut output,
+        input,
+        spans,
+        |span| {
+            format!(
+                "<span class=\"{}\" title=\"{}\">",
+                span.class.css_class(),
+                span.tooltip()
+            )
+        },
+        "</span>",
+        |output, c| html_escape(output, c.encode_utf8(&mut [0; 4])),
+    );
+    output.push_str("</pre>\n</body>\n</html>\n");
+    output
+}
@@ -0,0 +1,368 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::{Component, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+fn stk1(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stk1"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("stk1-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn sample() -> Vec<u8> {
+    [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 200].concat()
+}
+
+#[test]
+fn files() {
+    let dir = temp_dir("files");
+    let path = dir.join("sample.txt");
+    let packed = dir.join("sample.txt.tek");
+    let path_str = path.to_str().unwrap();
+    let packed_str = packed.to_str().unwrap();
+    fs::write(&path, sample()).unwrap();
+
+    let output = stk1(&["compress", "-9", path_str], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!path.exists());
+    assert!(fs::metadata(&packed).unwrap().len() < sample().len() as u64);
+
+    assert_eq!(stk1(&["test", packed_str], b"").status.code(), Some(0));
+
+    let output = stk1(&["d", "--keep", packed_str], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(fs::read(&path).unwrap(), sample());
+    assert!(packed.exists());
+
+    // Existing output
+    let output = stk1(&["d", packed_str], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    assert_eq!(stk1(&["d", "-f", packed_str], b"").status.code(), Some(0));
+    assert!(!packed.exists());
+
+    // Already compressed and unknown suffix
+    fs::write(&packed, b"").unwrap();
+    assert_eq!(stk1(&["c", packed_str], b"").status.code(), Some(2));
+    assert_eq!(stk1(&["d", path_str], b"").status.code(), Some(2));
+    assert_eq!(stk1(&["d", "-q", path_str], b"").stderr, b"");
+
+    // Corrupted data
+    fs::write(&packed, b"\x83\xFF").unwrap();
+    assert_eq!(stk1(&["test", packed_str], b"").status.code(), Some(1));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tek1_limit() {
+    let input = vec![0; 0x80_0001];
+    let output = stk1(&["compress", "-c", "--force"], &input);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--raw"));
+
+    let output = stk1(&["compress", "-c", "--force", "--raw"], &input);
+    assert_eq!(output.status.code(), Some(0));
+    let size = input.len().to_string();
+    let output = stk1(
+        &["d", "-c", "--force", "--raw", "--size", &size],
+        &output.stdout,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout == input);
+}
+
+#[test]
+fn stdio() {
+    let compressed = stk1(&["compress", "--level=1"], &sample());
+    assert_eq!(compressed.status.code(), Some(0));
+    assert!(compressed.stdout.starts_with(b"\x83\xFF\xFF\xFF"));
+    let decompressed = stk1(&["decompress", "-"], &compressed.stdout);
+    assert_eq!(decompressed.status.code(), Some(0));
+    assert_eq!(decompressed.stdout, sample());
+
+    let raw = stk1(&["c", "--raw", "--window", "tiny"], &sample());
+    assert_eq!(raw.status.code(), Some(0));
+    let size = sample().len().to_string();
+    let decompressed = stk1(&["d", "--raw", "--size", &size], &raw.stdout);
+    assert_eq!(decompressed.stdout, sample());
+    assert_eq!(stk1(&["d", "--raw"], &raw.stdout).status.code(), Some(1));
+
+    let mut corrupted = compressed.stdout.clone();
+    corrupted.truncate(corrupted.len() / 2);
+    let output = stk1(&["t"], &corrupted);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("decompression failed"));
+
+    assert_eq!(stk1(&["frobnicate"], b"").status.code(), Some(1));
+    assert_eq!(stk1(&["c", "--level", "10"], b"").status.code(), Some(1));
+}
+
+#[test]
+fn inspect() {
+    let compressed = stk1(&["compress"], &sample()).stdout;
+    let output = stk1(&["inspect"], &compressed);
+    assert_eq!(output.status.code(), Some(0));
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("format: tek1"));
+    assert!(text.contains(&format!("size: {}", sample().len())));
+    assert!(text.contains("literals \"The quick brown \"..."));
+    // Output ranges are half-open as in JSON
+    let literals = text.lines().find(|v| v.contains("literals \"")).unwrap();
+    assert!(literals.contains("00000000..00000020 "), "{}", literals);
+
+    let output = stk1(&["inspect", "--json", "--summary"], &compressed);
+    let json = String::from_utf8(output.stdout).unwrap();
+    assert!(json.starts_with("{\"file\":\"-\",\"format\":\"tek1\""));
+    assert!(json.contains(&format!("\"decoded\":{}", sample().len())));
+    assert!(json.contains("\"tokens\":[]"));
+    assert!(json.trim_end().ends_with("\"error\":null}"));
+
+    let output = stk1(&["inspect", "--json"], &compressed[..compressed.len() - 1]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\"error\":{\"offset\":"));
+}
+
+#[test]
+fn bench() {
+    let dir = temp_dir("bench");
+    fs::create_dir(dir.join("nested")).unwrap();
+    fs::write(dir.join("a.txt"), sample()).unwrap();
+    fs::write(dir.join("nested").join("b.txt"), sample()).unwrap();
+    let dir_str = dir.to_str().unwrap();
+
+    let output = stk1(
+        &[
+            "bench",
+            "--repeat",
+            "2",
+            "--levels",
+            "1,6",
+            "--windows",
+            "tiny",
+            dir_str,
+        ],
+        b"",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with(&format!(
+        "2 files, {} bytes, 2 runs each",
+        sample().len() * 2
+    )));
+    assert_eq!(text.lines().filter(|v| v.starts_with("tiny")).count(), 2);
+
+    let output = stk1(
+        &["bench", "--json", "--repeat=1", "--levels=9", dir_str],
+        b"",
+    );
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(text.lines().count(), 4);
+    assert!(text.lines().all(|v| v.starts_with("{\"window\":")));
+
+    assert_eq!(
+        stk1(&["bench", "--levels", "0-3", dir_str], b"")
+            .status
+            .code(),
+        Some(1)
+    );
+    assert_eq!(stk1(&["bench"], b"").status.code(), Some(1));
+    assert_eq!(
+        stk1(&["bench", "--decoder", "z80", dir_str], b"")
+            .status
+            .code(),
+        Some(1)
+    );
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    assert_eq!(
+        stk1(
+            &[
+                "bench",
+                "--decoder=x86_64",
+                "--repeat=1",
+                "--levels=1",
+                dir_str
+            ],
+            b""
+        )
+        .status
+        .code(),
+        Some(0)
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn visualize() {
+    let output = stk1(&["visualize"], &sample());
+    assert_eq!(output.status.code(), Some(0));
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with(&format!("-: {} => ", sample().len())));
+    assert!(text.contains("\x1b[31m 54 68 65"));
+    assert!(text.contains("\x1b[36m"));
+    assert_eq!(
+        text.lines().filter(|v| v.starts_with('0')).count(),
+        sample().len().div_ceil(16)
+    );
+
+    let output = stk1(&["v", "--html"], &sample());
+    assert_eq!(output.status.code(), Some(0));
+    let html = String::from_utf8(output.stdout).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<span class=\"l\" title=\"literals 0x0..0x"));
+    assert!(html.contains("(distance 45, length "));
+    assert!(html.trim_end().ends_with("</html>"));
+}
+
+#[test]
+fn archive() {
+    let dir = temp_dir("archive");
+    let input = dir.join("input");
+    fs::create_dir_all(input.join("nested")).unwrap();
+    fs::write(input.join("a.txt"), sample()).unwrap();
+    fs::write(input.join("nested").join("b.txt"), b"hello").unwrap();
+    let input_str = input.to_str().unwrap();
+
+    for (name, solid) in [("plain.stka", false), ("solid.stka", true)] {
+        let archive = dir.join(name);
+        let archive_str = archive.to_str().unwrap();
+        let mut args = vec!["pack", archive_str, input_str];
+        if solid {
+            args.push("--solid");
+        }
+        assert_eq!(stk1(&args, b"").status.code(), Some(0));
+        // The archive is not overwritten without --force
+        assert_eq!(stk1(&args, b"").status.code(), Some(1));
+
+        let output = stk1(&["list", archive_str], b"");
+        assert_eq!(output.status.code(), Some(0));
+        let text = String::from_utf8(output.stdout).unwrap();
+        assert!(text.contains("/input/a.txt\n"));
+        assert!(text.contains(&format!("{} ", sample().len())));
+        assert!(text.contains("/input/nested/b.txt\n"));
+        assert_eq!(text.contains("(solid)"), solid);
+
+        let output = stk1(&["list", "--json", archive_str], b"");
+        let json = String::from_utf8(output.stdout).unwrap();
+        assert!(json.contains(&format!("\"solid\":{}", solid)));
+        assert!(json.contains("/input/nested/b.txt\",\"size\":5,"));
+
+        let output_dir = dir.join(format!("output-{}", solid));
+        let output = stk1(
+            &["unpack", "-C", output_dir.to_str().unwrap(), archive_str],
+            b"",
+        );
+        assert_eq!(output.status.code(), Some(0));
+        // Entries of absolute paths are stored without the root
+        let unpacked = input
+            .components()
+            .filter(|v| matches!(v, Component::Normal(_)))
+            .fold(output_dir, |path, v| path.join(v));
+        assert_eq!(fs::read(unpacked.join("a.txt")).unwrap(), sample());
+        assert_eq!(
+            fs::read(unpacked.join("nested").join("b.txt")).unwrap(),
+            b"hello"
+        );
+    }
+
+    let output = stk1(&["list"], &sample());
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stk1(&["pack", "only.stka"], b"").status.code(), Some(1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[cfg(unix)]
+#[test]
+fn archive_links() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let dir = temp_dir("archive-links");
+    let input = dir.join("input");
+    fs::create_dir_all(&input).unwrap();
+    fs::write(input.join("a.txt"), b"hello").unwrap();
+    fs::set_permissions(input.join("a.txt"), fs::Permissions::from_mode(0o4755)).unwrap();
+    // A loop of symbolic links is skipped
+    symlink(&input, input.join("loop")).unwrap();
+    let archive = dir.join("links.stka");
+    let archive_str = archive.to_str().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_stk1"))
+        .args(["pack", archive_str, "input"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let output = stk1(&["list", archive_str], b"");
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("4755 "));
+    assert!(text.contains("1 files"));
+
+    // The setuid bit is not restored
+    let output_dir = dir.join("output");
+    let output_str = output_dir.to_str().unwrap();
+    let output = stk1(&["unpack", "-C", output_str, archive_str], b"");
+    assert_eq!(output.status.code(), Some(0));
+    let unpacked = output_dir.join("input").join("a.txt");
+    let mode = fs::metadata(&unpacked).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o755);
+
+    // Symbolic links at the destination are not followed, even with --force
+    let target = dir.join("target.txt");
+    fs::write(&target, b"target").unwrap();
+    fs::remove_file(&unpacked).unwrap();
+    symlink(&target, &unpacked).unwrap();
+    let output = stk1(&["unpack", "-f", "-C", output_str, archive_str], b"");
+    assert_eq!(output.status.code(), Some(1));
+    fs::remove_dir_all(output_dir.join("input")).unwrap();
+    symlink(&dir, output_dir.join("input")).unwrap();
+    let output = stk1(&["unpack", "-f", "-C", output_str, archive_str], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("symbolic link"));
+    assert_eq!(fs::read(&target).unwrap(), b"target");
+    assert_eq!(fs::read(input.join("a.txt")).unwrap(), b"hello");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sfx() {
+    let dir = temp_dir("sfx");
+    let file = dir.join("sample.txt");
+    fs::write(&file, sample()).unwrap();
+    let output = stk1(&["sfx", file.to_str().unwrap()], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(file.exists());
+
+    let output = Command::new(dir.join("sample.txt.sfx")).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout == sample());
+
+    let script = dir.join("script");
+    fs::write(&script, b"#!/bin/sh\necho \"hello $1\"\n").unwrap();
+    let output = stk1(&["sfx", "--exec", "-9", script.to_str().unwrap()], b"");
+    assert_eq!(output.status.code(), Some(0));
+    let output = Command::new(dir.join("script.sfx"))
+        .arg("world")
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, b"hello world\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
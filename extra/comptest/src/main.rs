@@ -15,6 +15,13 @@ fn usage() {
     let path = Path::new(&arg);
     let lpc = path.file_name().unwrap();
     eprintln!("{} [OPTIONS] INFILE OUTFILE", lpc.to_str().unwrap());
+    eprintln!();
+    eprintln!("OPTIONS:");
+    eprintln!("  -dry        Allow OUTFILE to be omitted");
+    eprintln!("  -stats      Print statistics of the encoder");
+    eprintln!("  -tiny       Use the tiny window");
+    eprintln!("  -large      Use the large window");
+    eprintln!("  -level N    Compression level 1-9 (default: 6)");
     process::exit(1);
 }
 
@@ -25,6 +32,8 @@ fn main() {
     let mut in_file = None;
     let mut config = Configuration::DEFAULT;
     let mut dry = false;
+    let mut level = None;
+    let mut stats = false;
 
     while let Some(arg) = args.next() {
         if arg.starts_with("-") {
@@ -34,7 +43,13 @@ fn main() {
                     break;
                 }
                 "-dry" => dry = true,
+                "-stats" => stats = true,
                 "-tiny" => config = Configuration::TINY,
+                "-large" => config = Configuration::LARGE,
+                "-level" => match args.next().and_then(|v| v.parse().ok()) {
+                    Some(v) => level = Some(v),
+                    None => return usage(),
+                },
                 _ => return usage(),
             }
         } else {
@@ -46,6 +61,9 @@ fn main() {
         Some(v) => v,
         None => return usage(),
     };
+    if let Some(level) = level {
+        config = config.level(level);
+    }
     let out_file = args.next();
     if !dry && out_file.is_none() {
         return usage();
@@ -67,8 +85,13 @@ fn main() {
         elapsed.as_secs_f64()
     );
 
+    if stats {
+        let (_, stats) = Stk1::encode_with_stats(&src, config).unwrap();
+        print!("{}", stats);
+    }
+
     if let Some(out_file) = out_file {
         let mut os = File::create(out_file).unwrap();
-        let _ = os.write_all(&dst).unwrap();
+        os.write_all(&dst).unwrap();
     }
 }
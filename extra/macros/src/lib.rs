@@ -0,0 +1,114 @@
+//! Compile-time compression for libstk1
+//!
+//! ```ignore
+//! use libstk1::Stk1Embedded;
+//! use libstk1_macros::include_stk1;
+//!
+//! const TABLE: Stk1Embedded = include_stk1!("data/table.bin");
+//!
+//! static mut BUFFER: [u8; TABLE.len()] = [0; TABLE.len()];
+//! ```
+//!
+//! The crate using the macro must also depend on `libstk1`.
+
+use libstk1::{Configuration, Stk1};
+use proc_macro::{Delimiter, Literal, Span, TokenStream, TokenTree};
+use std::{env, fs, path::PathBuf};
+
+/// Compresses a file at compile time and expands to a [`libstk1::Stk1Embedded`].
+///
+/// The path is relative to the directory of the `Cargo.toml` of the crate being compiled.
+/// The crate is rebuilt when the file changes.
+#[proc_macro]
+pub fn include_stk1(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(output) => output,
+        Err((message, span)) => compile_error(&message, span),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, (String, Span)> {
+    let (path, span) = parse_path(input)?;
+    let path = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir).join(path),
+        None => PathBuf::from(path),
+    };
+    let data =
+        fs::read(&path).map_err(|e| (format!("couldn't read {}: {}", path.display(), e), span))?;
+    let encoded = Stk1::encode(&data, Configuration::LARGE)
+        .map_err(|e| (format!("couldn't compress {}: {}", path.display(), e), span))?;
+    let path = path
+        .to_str()
+        .ok_or_else(|| (format!("{}: path is not UTF-8", path.display()), span))?;
+
+    // `include_bytes!` of the unused constant tells the compiler to track the file.
+    let output = format!(
+        "{{ const _: &[u8] = ::core::include_bytes!({}); ::libstk1::Stk1Embedded::new({}, {}) }}",
+        Literal::string(path),
+        Literal::byte_string(&encoded),
+        data.len()
+    );
+    Ok(output.parse().unwrap())
+}
+
+/// Reads the only argument, a string literal.
+fn parse_path(input: TokenStream) -> Result<(String, Span), (String, Span)> {
+    let mut tokens = input.into_iter().collect::<Vec<_>>();
+    // Arguments passed through `macro_rules!` may be in a group without delimiters.
+    while let [TokenTree::Group(group)] = tokens.as_slice() {
+        if group.delimiter() != Delimiter::None {
+            break;
+        }
+        tokens = group.stream().into_iter().collect();
+    }
+    let literal = match tokens.as_slice() {
+        [TokenTree::Literal(literal)] => literal,
+        [token, ..] => return Err(("expected a string literal".into(), token.span())),
+        [] => return Err(("expected a string literal".into(), Span::call_site())),
+    };
+    let span = literal.span();
+    let text = literal.to_string();
+    unquote(&text)
+        .ok_or_else(|| ("expected a string literal".into(), span))
+        .map(|v| (v, span))
+}
+
+/// Returns the value of a string literal or a raw string literal.
+fn unquote(text: &str) -> Option<String> {
+    if let Some(raw) = text.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let raw = &raw[hashes..raw.len().checked_sub(hashes)?];
+        return Some(raw.strip_prefix('"')?.strip_suffix('"')?.to_owned());
+    }
+    let text = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        value.push(match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            c @ ('\\' | '"' | '\'') => c,
+            _ => return None,
+        });
+    }
+    Some(value)
+}
+
+fn compile_error(message: &str, span: Span) -> TokenStream {
+    let output = format!("::core::compile_error!({})", Literal::string(message));
+    output
+        .parse::<TokenStream>()
+        .unwrap()
+        .into_iter()
+        .map(|mut token| {
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
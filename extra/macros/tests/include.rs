@@ -0,0 +1,25 @@
+use libstk1::Stk1Embedded;
+use libstk1_macros::include_stk1;
+
+const TABLE: Stk1Embedded = include_stk1!("tests/table.txt");
+
+macro_rules! include_table {
+    ($path:expr) => {
+        include_stk1!($path)
+    };
+}
+
+#[test]
+fn include() {
+    let expected = include_bytes!("table.txt");
+    assert_eq!(TABLE.len(), expected.len());
+    assert!(TABLE.data().len() < expected.len() / 2);
+    assert_eq!(TABLE.decode_to_vec().unwrap(), expected);
+
+    let mut buffer = [0; TABLE.len()];
+    TABLE.decode(&mut buffer).unwrap();
+    assert_eq!(buffer, *expected);
+    assert!(TABLE.decode(&mut buffer[1..]).is_err());
+
+    assert_eq!(include_table!(r"tests/table.txt"), TABLE);
+}
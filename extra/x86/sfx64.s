@@ -0,0 +1,190 @@
+## Self-extracting stub for x86-64 Linux
+##
+## `$ as --64 -o sfx64.o sfx64.s && objcopy -O binary -j .text sfx64.o sfx64.bin`
+##
+## The bytes of sfx64.bin are `Sfx::STUB` in `src/sfx.rs`, which places them after the ELF header
+## and fills in the parameters at the end of the stub. The compressed data follows the parameters.
+##
+## The decoder matches `Stk1::decode` including its checks of invalid data.
+## The stub exits with status 127 if the data is invalid or a system call fails.
+##
+## LICENSE: PUBLIC DOMAIN
+##
+
+    .intel_syntax noprefix
+    .text
+
+## Registers while decoding
+##
+## * RSI - compressed data, R14 - end of compressed data
+## * RBX - decompressed data, RDI - cursor, R13 - end of decompressed data
+## * R12 - initial stack pointer (argc, argv and envp)
+
+_start:
+    mov r12, rsp
+    mov rsi, [rip + size]
+    inc rsi
+    xor edi, edi
+    mov edx, 3                          # PROT_READ | PROT_WRITE
+    mov r10d, 0x22                      # MAP_PRIVATE | MAP_ANONYMOUS
+    or r8, -1
+    xor r9d, r9d
+    mov eax, 9                          # mmap
+    syscall
+    cmp rax, -4096
+    ja fail
+    mov rbx, rax
+    mov rdi, rax
+    mov r13, rax
+    add r13, [rip + size]
+    lea rsi, [rip + data]
+    mov r14, rsi
+    add r14, [rip + compressed_len]
+
+group:
+    cmp rdi, r13
+    jae done
+    call next
+    mov ebp, eax
+    and eax, 0x0F
+    jnz 1f
+    call s7s
+1:
+    mov r15, rax                        # literals
+    mov eax, ebp
+    shr eax, 4
+    jnz 2f
+    call s7s
+2:
+    mov rbp, rax                        # matches
+
+literals:
+    test r15, r15
+    jz 3f
+    cmp rsi, r14
+    jae 3f
+    cmp rdi, r13
+    jae fail
+    movsb
+    dec r15
+    jmp literals
+3:
+    cmp rdi, r13
+    jae done
+
+matches:
+    test rbp, rbp
+    jz group
+    call next
+    mov r15, rax
+    and eax, 0x0F
+    call s7s_acc
+    lea rcx, [rax + 1]                  # distance
+    mov eax, r15d
+    shr eax, 4
+    jnz 4f
+    call s7s
+4:
+    inc rax                             # length
+    mov rdx, rdi
+    sub rdx, rbx
+    cmp rcx, rdx
+    ja fail
+    mov rdx, r13
+    sub rdx, rdi
+    cmp rax, rdx
+    cmova rax, rdx
+    push rsi
+    mov rsi, rdi
+    sub rsi, rcx
+    mov rcx, rax
+    rep movsb
+    pop rsi
+    dec rbp
+    jmp matches
+
+## Reads a byte into RAX, or fails at the end of the compressed data.
+next:
+    cmp rsi, r14
+    jae fail
+    movzx eax, byte ptr [rsi]
+    inc rsi
+    ret
+
+## Reads S7s into RAX, starting with the accumulator in RAX for `s7s_acc`. Destroys RDX.
+s7s:
+    xor eax, eax
+s7s_acc:
+    test al, 1
+    jnz 5f
+    mov rdx, rax
+    call next
+    shl rdx, 7
+    or rax, rdx
+    jmp s7s_acc
+5:
+    shr rax, 1
+    ret
+
+done:
+    cmp qword ptr [rip + mode], 0
+    jne exec
+    mov edi, 1                          # standard output
+    call write_all
+    xor edi, edi
+    jmp exit
+
+exec:
+    lea rdi, [rip + name]
+    xor esi, esi
+    mov eax, 319                        # memfd_create
+    syscall
+    test rax, rax
+    js fail
+    mov r15, rax
+    mov edi, eax
+    call write_all
+    mov edi, r15d
+    lea rsi, [rip + name + 3]           # empty path
+    lea rdx, [r12 + 8]                  # argv
+    mov r10, [r12]
+    lea r10, [rdx + r10 * 8 + 8]        # envp
+    mov r8d, 0x1000                     # AT_EMPTY_PATH
+    mov eax, 322                        # execveat
+    syscall
+
+fail:
+    mov edi, 127
+exit:
+    mov eax, 231                        # exit_group
+    syscall
+
+## Writes the decompressed data to the file descriptor in EDI.
+write_all:
+    mov rsi, rbx
+    mov rdx, [rip + size]
+6:
+    test rdx, rdx
+    jz 7f
+    mov eax, 1                          # write
+    syscall
+    test rax, rax
+    jle fail
+    add rsi, rax
+    sub rdx, rax
+    jmp 6b
+7:
+    ret
+
+name:
+    .asciz "sfx"
+
+## Parameters filled in by `Sfx`
+    .balign 8
+size:
+    .quad 0
+compressed_len:
+    .quad 0
+mode:
+    .quad 0
+data:
@@ -0,0 +1,458 @@
+//! Multi-file archive
+
+use crate::{Configuration, DecodeError, EncodeError, S7s, Stk1, Stk1Encoder};
+use alloc::{string::String, vec::Vec};
+
+/// An archive of files compressed with stk1
+///
+/// The archive begins with [`Archive::SIGNATURE`], the flags in S7s and the number of entries in S7s,
+/// followed by the directory and the compressed data.
+/// Each entry of the directory has the following fields:
+///
+/// * Length of the path in S7s and the path in UTF-8, whose components are separated by `/`
+/// * Size in S7s
+/// * Mode in S7s
+/// * Modification time in seconds since the Unix epoch, in 8 bytes of little endian
+/// * CRC-32 of the data, in 4 bytes of little endian
+/// * Compressed length in S7s
+///
+/// Normally the data of each entry is compressed separately and stored in the order of the directory.
+/// In solid mode, the compressed lengths in the directory are zero and the data of all entries
+/// is concatenated and compressed as one stream, whose length in S7s precedes it,
+/// so that entries can match each other.
+pub struct Archive;
+
+impl Archive {
+    /// Signature at the beginning of the archive
+    pub const SIGNATURE: [u8; 8] = *b"STK1ARC\0";
+
+    const FLAG_SOLID: usize = 0x01;
+
+    /// Returns the CRC-32 (ISO-HDLC) of `data`.
+    pub fn crc32(data: &[u8]) -> u32 {
+        !data.iter().fold(!0, |crc, &byte| {
+            CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+        })
+    }
+
+    /// Returns whether the path is relative, nonempty and free of `.` and `..` components.
+    pub fn is_valid_path(path: &str) -> bool {
+        !path.is_empty()
+            && !path.contains(['\\', '\0'])
+            && path
+                .split('/')
+                .all(|v| !v.is_empty() && v != "." && v != "..")
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if (crc & 1) != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+/// An entry of the archive directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Relative path whose components are separated by `/`
+    pub path: String,
+    /// Decompressed size
+    pub size: usize,
+    /// Unix permission bits
+    pub mode: u32,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: u64,
+    /// CRC-32 of the data
+    pub crc32: u32,
+    /// Compressed length, which is zero in solid mode
+    pub compressed_len: usize,
+}
+
+/// Builds an archive in memory
+pub struct ArchiveWriter {
+    encoder: Stk1Encoder,
+    solid: bool,
+    entries: Vec<ArchiveEntry>,
+    /// Compressed data of each entry, or the uncompressed data of all entries in solid mode
+    data: Vec<u8>,
+}
+
+impl ArchiveWriter {
+    #[inline]
+    pub const fn new(config: Configuration) -> Self {
+        Self {
+            encoder: Stk1Encoder::new(config),
+            solid: false,
+            entries: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Sets whether all entries are compressed as one stream.
+    #[inline]
+    pub fn solid(mut self, solid: bool) -> Self {
+        self.solid = solid;
+        self
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// Adds an entry.
+    ///
+    /// Fails with [`EncodeError::InvalidPath`] unless [`Archive::is_valid_path`] accepts `path`.
+    pub fn add(
+        &mut self,
+        path: &str,
+        mode: u32,
+        mtime: u64,
+        data: &[u8],
+    ) -> Result<(), EncodeError> {
+        if !Archive::is_valid_path(path) {
+            return Err(EncodeError::InvalidPath);
+        }
+        let mut owned_path = String::new();
+        owned_path
+            .try_reserve_exact(path.len())
+            .map_err(|_| EncodeError::OutOfMemory)?;
+        owned_path.push_str(path);
+        self.entries
+            .try_reserve(1)
+            .map_err(|_| EncodeError::OutOfMemory)?;
+        let compressed_len = if self.solid {
+            self.data
+                .try_reserve(data.len())
+                .map_err(|_| EncodeError::OutOfMemory)?;
+            self.data.extend_from_slice(data);
+            0
+        } else {
+            let len = self.data.len();
+            if let Err(e) = self.encoder.encode_into(data, &mut self.data) {
+                // Remove the partial stream so that the writer stays usable
+                self.data.truncate(len);
+                return Err(e);
+            }
+            self.data.len() - len
+        };
+        self.entries.push(ArchiveEntry {
+            path: owned_path,
+            size: data.len(),
+            mode,
+            mtime,
+            crc32: Archive::crc32(data),
+            compressed_len,
+        });
+        Ok(())
+    }
+
+    /// Returns the archive.
+    pub fn finish(mut self) -> Result<Vec<u8>, EncodeError> {
+        let flags = if self.solid { Archive::FLAG_SOLID } else { 0 };
+        if self.solid {
+            let mut stream = Vec::new();
+            self.encoder.encode_into(&self.data, &mut stream)?;
+            self.data = stream;
+        }
+
+        // Reserve the whole archive so that the writes below do not allocate
+        let directory_len = self
+            .entries
+            .iter()
+            .map(|entry| {
+                S7s::encoded_len(entry.path.len())
+                    + entry.path.len()
+                    + S7s::encoded_len(entry.size)
+                    + S7s::encoded_len(entry.mode as usize)
+                    + 8
+                    + 4
+                    + S7s::encoded_len(entry.compressed_len)
+            })
+            .sum::<usize>();
+        let stream_len = if self.solid {
+            S7s::encoded_len(self.data.len())
+        } else {
+            0
+        };
+        let len = Archive::SIGNATURE.len()
+            + S7s::encoded_len(flags)
+            + S7s::encoded_len(self.entries.len())
+            + directory_len
+            + stream_len
+            + self.data.len();
+        let mut output = Vec::new();
+        output
+            .try_reserve_exact(len)
+            .map_err(|_| EncodeError::OutOfMemory)?;
+
+        output.extend_from_slice(&Archive::SIGNATURE);
+        S7s::write(&mut output, flags);
+        S7s::write(&mut output, self.entries.len());
+        for entry in &self.entries {
+            S7s::write(&mut output, entry.path.len());
+            output.extend_from_slice(entry.path.as_bytes());
+            S7s::write(&mut output, entry.size);
+            S7s::write(&mut output, entry.mode as usize);
+            output.extend_from_slice(&entry.mtime.to_le_bytes());
+            output.extend_from_slice(&entry.crc32.to_le_bytes());
+            S7s::write(&mut output, entry.compressed_len);
+        }
+        if self.solid {
+            S7s::write(&mut output, self.data.len());
+        }
+        output.extend_from_slice(&self.data);
+        debug_assert_eq!(output.len(), len);
+        Ok(output)
+    }
+}
+
+/// Reads an archive in memory
+pub struct ArchiveReader<'a> {
+    entries: Vec<ArchiveEntry>,
+    solid: bool,
+    /// Compressed data following the directory
+    data: &'a [u8],
+}
+
+impl<'a> ArchiveReader<'a> {
+    /// Reads the directory of the archive.
+    pub fn new(input: &'a [u8]) -> Result<Self, DecodeError> {
+        let body = input
+            .strip_prefix(&Archive::SIGNATURE)
+            .ok_or(DecodeError::InvalidData)?;
+        let mut iter = body.iter();
+        let iter = &mut iter;
+        let flags = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+        if (flags & !Archive::FLAG_SOLID) != 0 {
+            return Err(DecodeError::InvalidData);
+        }
+        let solid = (flags & Archive::FLAG_SOLID) != 0;
+        let count = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+
+        let mut entries = Vec::new();
+        let mut data_len = 0usize;
+        for _ in 0..count {
+            let path_len = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+            let path = Self::_take(iter, path_len)?;
+            let path = core::str::from_utf8(path).map_err(|_| DecodeError::InvalidData)?;
+            if !Archive::is_valid_path(path) {
+                return Err(DecodeError::InvalidData);
+            }
+            let size = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+            let mode = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+            let mode = u32::try_from(mode).map_err(|_| DecodeError::InvalidData)?;
+            let mtime = u64::from_le_bytes(Self::_take(iter, 8)?.try_into().unwrap());
+            let crc32 = u32::from_le_bytes(Self::_take(iter, 4)?.try_into().unwrap());
+            let compressed_len = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+            if solid && compressed_len != 0 {
+                return Err(DecodeError::InvalidData);
+            }
+            data_len = data_len
+                .checked_add(compressed_len)
+                .ok_or(DecodeError::InvalidData)?;
+            entries
+                .try_reserve(1)
+                .map_err(|_| DecodeError::OutOfMemory)?;
+            entries.push(ArchiveEntry {
+                path: path.into(),
+                size,
+                mode,
+                mtime,
+                crc32,
+                compressed_len,
+            });
+        }
+        if solid {
+            data_len = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+        }
+        let data = iter.as_slice();
+        if data.len() != data_len {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(Self {
+            entries,
+            solid,
+            data,
+        })
+    }
+
+    fn _take(iter: &mut core::slice::Iter<'a, u8>, len: usize) -> Result<&'a [u8], DecodeError> {
+        let slice = iter.as_slice();
+        let result = slice.get(..len).ok_or(DecodeError::InvalidData)?;
+        *iter = slice[len..].iter();
+        Ok(result)
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn is_solid(&self) -> bool {
+        self.solid
+    }
+
+    /// Decodes the data of the entry at `index` and verifies its CRC.
+    ///
+    /// In solid mode, the whole stream is decoded; use [`ArchiveReader::read_all`] to read all entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn read(&self, index: usize) -> Result<Vec<u8>, DecodeError> {
+        let entry = &self.entries[index];
+        let data = if self.solid {
+            let offset = self.entries[..index].iter().map(|v| v.size).sum::<usize>();
+            let mut stream = self._decode_solid()?;
+            stream.truncate(offset + entry.size);
+            stream.drain(..offset);
+            stream
+        } else {
+            let offset = self.entries[..index]
+                .iter()
+                .map(|v| v.compressed_len)
+                .sum::<usize>();
+            Stk1::decode_to_vec(&self.data[offset..][..entry.compressed_len], entry.size)?
+        };
+        Self::_verify(entry, data)
+    }
+
+    /// Decodes the data of all entries in the order of the directory and verifies their CRCs.
+    pub fn read_all(&self) -> Result<Vec<Vec<u8>>, DecodeError> {
+        let mut result = Vec::new();
+        result
+            .try_reserve(self.entries.len())
+            .map_err(|_| DecodeError::OutOfMemory)?;
+        if self.solid {
+            let stream = self._decode_solid()?;
+            let mut offset = 0;
+            for entry in &self.entries {
+                let data = stream[offset..][..entry.size].to_vec();
+                offset += entry.size;
+                result.push(Self::_verify(entry, data)?);
+            }
+        } else {
+            for index in 0..self.entries.len() {
+                result.push(self.read(index)?);
+            }
+        }
+        Ok(result)
+    }
+
+    fn _decode_solid(&self) -> Result<Vec<u8>, DecodeError> {
+        let size = self
+            .entries
+            .iter()
+            .try_fold(0usize, |acc, v| acc.checked_add(v.size))
+            .ok_or(DecodeError::InvalidData)?;
+        Stk1::decode_to_vec(self.data, size)
+    }
+
+    #[inline]
+    fn _verify(entry: &ArchiveEntry, data: Vec<u8>) -> Result<Vec<u8>, DecodeError> {
+        if Archive::crc32(&data) == entry.crc32 {
+            Ok(data)
+        } else {
+            Err(DecodeError::InvalidData)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{Archive, ArchiveReader, ArchiveWriter, Configuration, DecodeError, EncodeError};
+
+    #[test]
+    fn crc32() {
+        assert_eq!(Archive::crc32(b""), 0);
+        assert_eq!(Archive::crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let files: [(&str, &[u8]); 3] = [
+            ("README", b"The quick brown fox jumps over the lazy dog."),
+            ("bin/empty", b""),
+            ("bin/data", &[0x55; 1000]),
+        ];
+        for solid in [false, true] {
+            let mut writer = ArchiveWriter::new(Configuration::DEFAULT).solid(solid);
+            for (index, (path, data)) in files.iter().enumerate() {
+                writer.add(path, 0o644, index as u64, data).unwrap();
+            }
+            let archive = writer.finish().unwrap();
+
+            let reader = ArchiveReader::new(&archive).unwrap();
+            assert_eq!(reader.is_solid(), solid);
+            assert_eq!(reader.entries().len(), files.len());
+            let all = reader.read_all().unwrap();
+            for (index, (path, data)) in files.iter().enumerate() {
+                let entry = &reader.entries()[index];
+                assert_eq!((entry.path.as_str(), entry.size), (*path, data.len()));
+                assert_eq!((entry.mode, entry.mtime), (0o644, index as u64));
+                assert_eq!(all[index], *data);
+                assert_eq!(reader.read(index).unwrap(), *data);
+            }
+
+            // CRC of the first entry, after the path, size, mode and mtime
+            let mut corrupted = archive.clone();
+            corrupted[Archive::SIGNATURE.len() + 3 + 6 + 1 + 2 + 8] ^= 0x10;
+            let reader = ArchiveReader::new(&corrupted).unwrap();
+            assert!(matches!(reader.read(0), Err(DecodeError::InvalidData)));
+            assert!(reader.read_all().is_err());
+            assert!(reader.read(1).is_ok());
+            assert!(matches!(
+                ArchiveReader::new(&archive[..archive.len() - 1]),
+                Err(DecodeError::InvalidData)
+            ));
+        }
+    }
+
+    #[test]
+    fn failed_add() {
+        let text = b"The quick brown fox jumps over the lazy dog.";
+        let mut writer = ArchiveWriter::new(Configuration::DEFAULT.memory_limit(0x8000));
+        writer.add("a", 0o644, 0, text).unwrap();
+        assert!(matches!(
+            writer.add("b", 0o644, 0, &[0x55; 0x10000]),
+            Err(EncodeError::OutOfMemory)
+        ));
+        writer.add("c", 0o644, 0, text).unwrap();
+        let archive = writer.finish().unwrap();
+
+        let reader = ArchiveReader::new(&archive).unwrap();
+        assert_eq!(reader.entries().len(), 2);
+        assert_eq!(reader.entries()[1].path, "c");
+        assert_eq!(reader.read_all().unwrap(), [text, text]);
+    }
+
+    #[test]
+    fn paths() {
+        for path in ["a", "a/b", ".a", "a/..b"] {
+            assert!(Archive::is_valid_path(path), "{}", path);
+        }
+        for path in ["", "/a", "a/", "a//b", "../a", "a/./b", "a\\b"] {
+            assert!(!Archive::is_valid_path(path), "{}", path);
+            assert!(matches!(
+                ArchiveWriter::new(Configuration::DEFAULT).add(path, 0, 0, b""),
+                Err(EncodeError::InvalidPath)
+            ));
+        }
+    }
+}
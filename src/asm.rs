@@ -0,0 +1,253 @@
+//! Decoder in x86-64 assembly
+
+use crate::DecodeError;
+use core::arch::global_asm;
+
+// Decodes stk1 data with the same checks as `Stk1::decode`.
+//
+// `u32 libstk1_decode_x86_64(const u8 *input, usize input_len, u8 *output, usize output_len)`
+// returns 0 on success or 1 if the data is invalid.
+//
+// * RSI - input, R8 - end of input
+// * RDI - cursor, R9 - output, R10 - end of output
+// * R11 - remaining matches of the group, R12 - stack pointer to restore on errors
+global_asm!(
+    ".pushsection .text.libstk1_decode_x86_64, \"ax\", @progbits",
+    ".p2align 4",
+    ".globl libstk1_decode_x86_64",
+    ".hidden libstk1_decode_x86_64",
+    "libstk1_decode_x86_64:",
+    "    push rbx",
+    "    push r12",
+    "    mov r12, rsp",
+    "    lea r8, [rdi + rsi]",
+    "    mov rsi, rdi",
+    "    mov rdi, rdx",
+    "    mov r9, rdx",
+    "    lea r10, [rdx + rcx]",
+    ".Lstk1_group:",
+    "    cmp rdi, r10",
+    "    jae .Lstk1_ok",
+    "    call .Lstk1_next",
+    "    mov ebx, eax",
+    "    and eax, 0x0F",
+    "    jnz 1f",
+    "    call .Lstk1_s7s",
+    "1:",
+    "    mov rcx, rax",
+    "    mov eax, ebx",
+    "    shr eax, 4",
+    "    jnz 2f",
+    "    call .Lstk1_s7s",
+    "2:",
+    "    mov r11, rax",
+    // Literals are limited by the input, and must fit in the output
+    "    mov rax, r8",
+    "    sub rax, rsi",
+    "    cmp rcx, rax",
+    "    cmova rcx, rax",
+    "    mov rax, r10",
+    "    sub rax, rdi",
+    "    cmp rcx, rax",
+    "    ja .Lstk1_error",
+    "    rep movsb",
+    "    cmp rdi, r10",
+    "    jae .Lstk1_ok",
+    ".Lstk1_match:",
+    "    test r11, r11",
+    "    jz .Lstk1_group",
+    "    call .Lstk1_next",
+    "    mov ebx, eax",
+    "    and eax, 0x0F",
+    "    call .Lstk1_s7s_acc",
+    "    lea rcx, [rax + 1]",
+    "    mov eax, ebx",
+    "    shr eax, 4",
+    "    jnz 3f",
+    "    call .Lstk1_s7s",
+    "3:",
+    "    inc rax",
+    // The distance must be within the output, and the length is limited by the output
+    "    mov rdx, rdi",
+    "    sub rdx, r9",
+    "    cmp rcx, rdx",
+    "    ja .Lstk1_error",
+    "    mov rdx, r10",
+    "    sub rdx, rdi",
+    "    cmp rax, rdx",
+    "    cmova rax, rdx",
+    "    mov rdx, rsi",
+    "    mov rsi, rdi",
+    "    sub rsi, rcx",
+    "    mov rcx, rax",
+    "    rep movsb",
+    "    mov rsi, rdx",
+    "    dec r11",
+    "    jmp .Lstk1_match",
+    ".Lstk1_ok:",
+    "    xor eax, eax",
+    "    pop r12",
+    "    pop rbx",
+    "    ret",
+    ".Lstk1_error:",
+    "    mov rsp, r12",
+    "    mov eax, 1",
+    "    pop r12",
+    "    pop rbx",
+    "    ret",
+    // Reads a byte into RAX.
+    ".Lstk1_next:",
+    "    cmp rsi, r8",
+    "    jae .Lstk1_error",
+    "    movzx eax, byte ptr [rsi]",
+    "    inc rsi",
+    "    ret",
+    // Reads S7s into RAX, starting with the accumulator in RAX for `s7s_acc`. Destroys RDX.
+    ".Lstk1_s7s:",
+    "    xor eax, eax",
+    ".Lstk1_s7s_acc:",
+    "    test al, 1",
+    "    jnz 4f",
+    "    mov rdx, rax",
+    "    call .Lstk1_next",
+    "    shl rdx, 7",
+    "    or rax, rdx",
+    "    jmp .Lstk1_s7s_acc",
+    "4:",
+    "    shr rax, 1",
+    "    ret",
+    ".popsection",
+);
+
+extern "sysv64" {
+    fn libstk1_decode_x86_64(
+        input: *const u8,
+        input_len: usize,
+        output: *mut u8,
+        output_len: usize,
+    ) -> u32;
+}
+
+/// Decodes with the assembly decoder.
+#[inline]
+pub(crate) fn _decode(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+    // SAFETY: the decoder reads and writes only within the slices
+    let result = unsafe {
+        libstk1_decode_x86_64(
+            input.as_ptr(),
+            input.len(),
+            output.as_mut_ptr(),
+            output.len(),
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(DecodeError::InvalidData)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{Configuration, DecoderKind, Stk1};
+    use std::{fs, path::PathBuf};
+
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            // xorshift64*
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        fn bytes(&mut self, len: usize, alphabet: u64) -> Vec<u8> {
+            (0..len).map(|_| (self.next() % alphabet) as u8).collect()
+        }
+    }
+
+    /// Checks that both decoders return the same result and output.
+    fn check(input: &[u8], size: usize) -> bool {
+        let mut expected = vec![0; size];
+        let mut output = vec![0; size];
+        let result = Stk1::decode(input, &mut expected);
+        let asm_result = Stk1::decode_with(DecoderKind::X86_64, input, &mut output);
+        assert_eq!(
+            result.is_ok(),
+            asm_result.is_ok(),
+            "{:02x?} {}",
+            input,
+            size
+        );
+        if result.is_ok() {
+            assert!(expected == output, "{:02x?} {}", input, size);
+        }
+        result.is_ok()
+    }
+
+    #[test]
+    fn corpus() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut random = Random(0x1234_5678);
+        let mut inputs = vec![
+            Vec::new(),
+            vec![0; 100_000],
+            random.bytes(100_000, 256),
+            random.bytes(100_000, 4),
+        ];
+        for entry in fs::read_dir(dir).unwrap() {
+            inputs.push(fs::read(entry.unwrap().path()).unwrap());
+        }
+        for input in &inputs {
+            for config in [
+                Configuration::TINY.level(1),
+                Configuration::DEFAULT.level(9),
+            ] {
+                let encoded = Stk1::encode(input, config).unwrap();
+                assert!(check(&encoded, input.len()));
+                let mut output = vec![0; input.len()];
+                Stk1::decode_with(DecoderKind::X86_64, &encoded, &mut output).unwrap();
+                assert!(output == *input);
+                // Truncated data and wrong sizes
+                if !encoded.is_empty() {
+                    check(&encoded[..encoded.len() - 1], input.len());
+                }
+                check(&encoded, input.len() + 1);
+                check(&encoded, input.len() / 2);
+            }
+        }
+    }
+
+    #[test]
+    fn random() {
+        let mut random = Random(0x9E37_79B9_7F4A_7C15);
+        let mut valid = 0;
+        for _ in 0..20_000 {
+            let len = (random.next() % 64) as usize;
+            let mut input = random.bytes(len, 256);
+            // Small distances and lengths make valid data more likely
+            for byte in input.iter_mut() {
+                if random.next().is_multiple_of(2) {
+                    *byte &= 0x33;
+                }
+            }
+            let size = (random.next() % 256) as usize;
+            if check(&input, size) {
+                valid += 1;
+            }
+        }
+        assert!(valid > 100, "{}", valid);
+
+        // Corrupted encoded data
+        let text = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 20].concat();
+        let encoded = Stk1::encode(&text, Configuration::DEFAULT).unwrap();
+        for _ in 0..20_000 {
+            let mut input = encoded.clone();
+            let index = (random.next() as usize) % input.len();
+            input[index] ^= 1 << (random.next() % 8);
+            check(&input, text.len());
+        }
+    }
+}
@@ -1,30 +1,82 @@
 //! cache offsets of matching patterns
 
-use alloc::{boxed::Box, collections::BTreeMap};
-use core::mem::ManuallyDrop;
-
 pub type OffsetCache<'a> = MatchingCache<'a, MatchingBytesKey>;
 
+/// Marks an empty entry of the tables
+const NONE: usize = usize::MAX;
+
+/// Smallest number of bits of the hash table index
+pub const MIN_HASH_BITS: u32 = 8;
+/// Largest number of bits of the hash table index
+pub const MAX_HASH_BITS: u32 = 20;
+
+/// Shortest number of elements hashed as a key
+pub const MIN_KEY_LEN: usize = 3;
+/// Longest number of elements hashed as a key
+pub const MAX_KEY_LEN: usize = 5;
+
+/// Hash chains of offsets, kept in tables borrowed from the caller
+///
+/// The tables do not need to be cleared between inputs except for the part of `head` in use,
+/// so that they can be reused without reallocation.
 pub struct MatchingCache<'a, KEY>
 where
     KEY: MatchingKey,
 {
     input: &'a [KEY::ElementType],
     key: KEY,
-    cache: BTreeMap<KEY::KeyType, OffsetList>,
+    head: &'a mut [usize],
+    list: OffsetList<'a>,
+    hash_bits: u32,
+    key_len: usize,
     cursor: usize,
     limit: usize,
     max_distance: usize,
 }
 
 impl<'a, KEY: MatchingKey> MatchingCache<'a, KEY> {
+    /// Returns the number of entries of `head` and `links` required for the input,
+    /// with a hash table of up to `max_hash_bits` bits.
+    #[inline]
+    pub fn table_sizes(
+        input_len: usize,
+        max_distance: usize,
+        max_hash_bits: u32,
+    ) -> (usize, usize) {
+        let hash_bits = input_len
+            .min(max_distance)
+            .next_power_of_two()
+            .trailing_zeros()
+            .clamp(MIN_HASH_BITS, max_hash_bits.max(MIN_HASH_BITS));
+        let list_len = max_distance.min(input_len).next_power_of_two();
+        (1 << hash_bits, list_len)
+    }
+
+    /// Creates a match finder that hashes `key_len` elements at each position.
+    ///
+    /// The lengths of `head` and `links` are those returned by [`MatchingCache::table_sizes`].
     #[inline]
-    pub fn new(input: &'a [KEY::ElementType], max_distance: usize) -> Self {
-        if input.len() < 4 {
+    #[track_caller]
+    pub fn new(
+        input: &'a [KEY::ElementType],
+        max_distance: usize,
+        key_len: usize,
+        head: &'a mut [usize],
+        links: &'a mut [usize],
+    ) -> Self {
+        assert!((MIN_KEY_LEN..=MAX_KEY_LEN).contains(&key_len));
+        assert!(head.len().is_power_of_two());
+        head.fill(NONE);
+        let list = OffsetList::new(links);
+        let hash_bits = head.len().trailing_zeros();
+        if input.len() <= key_len {
             Self {
                 input,
                 key: KEY::null(),
-                cache: BTreeMap::new(),
+                head,
+                list,
+                hash_bits,
+                key_len,
                 cursor: 0,
                 limit: 0,
                 max_distance,
@@ -32,10 +84,13 @@ impl<'a, KEY: MatchingKey> MatchingCache<'a, KEY> {
         } else {
             Self {
                 input,
-                key: KEY::new(input[0], input[1], input[2]),
-                cache: BTreeMap::new(),
+                key: KEY::new(&input[..key_len]),
+                head,
+                list,
+                hash_bits,
+                key_len,
                 cursor: 0,
-                limit: input.len() - 2,
+                limit: input.len() - (key_len - 1),
                 max_distance,
             }
         }
@@ -50,234 +105,177 @@ impl<KEY: MatchingKey> MatchingCache<'_, KEY> {
             return;
         }
         for _ in 0..step {
-            self._insert(self.key.key_value(), cursor);
+            self._insert(self.key.hash(self.hash_bits), cursor);
             cursor += 1;
             if cursor >= limit {
                 break;
             }
-            self.key.advance(self.input[cursor + 2]);
+            self.key.advance(self.input[cursor + self.key_len - 1]);
         }
         self.cursor = cursor;
     }
 
+    /// Returns the distances of the candidates, nearest first.
+    ///
+    /// Since the keys are hashed, the candidates are not guaranteed to match.
     pub fn matches<'a>(&'a self) -> Option<impl Iterator<Item = usize> + 'a> {
         if self.cursor >= self.limit {
             return None;
         }
         let min_value = self.cursor.saturating_sub(self.max_distance);
-        self.cache
-            .get(&self.key.key_value())
-            .map(|v| v.distance_iter(self.cursor, min_value))
+        Some(self.list.distance_iter(
+            self.head[self.key.hash(self.hash_bits)],
+            self.cursor,
+            min_value,
+        ))
     }
 
-    fn _insert(&mut self, key: KEY::KeyType, value: usize) {
-        let value = value as u32;
-        match self.cache.get_mut(&key) {
-            Some(list) => {
-                list.push(value);
-            }
-            None => {
-                self.cache.insert(key, OffsetList::new(value));
-            }
-        }
-
-        if self.cache.len() >= self.max_distance * 2 {
-            let min_value = self.cursor.saturating_sub(self.max_distance) as u32;
-            self.cache.retain(|_k, v| v.retain(min_value))
-        }
+    #[inline]
+    fn _insert(&mut self, hash: usize, value: usize) {
+        self.list.push(value, self.head[hash]);
+        self.head[hash] = value;
     }
 }
 
 pub trait MatchingKey
 where
     Self::ElementType: Copy,
-    Self::KeyType: Copy + Ord,
 {
     type ElementType;
-    type KeyType;
 
     fn null() -> Self;
 
-    fn new(val0: Self::ElementType, val1: Self::ElementType, val2: Self::ElementType) -> Self;
-
-    fn key_value(&self) -> Self::KeyType;
+    /// Creates a key of `values`, whose length is between [`MIN_KEY_LEN`] and [`MAX_KEY_LEN`].
+    fn new(values: &[Self::ElementType]) -> Self;
 
     fn advance(&mut self, new_value: Self::ElementType);
+
+    /// Returns the hash value of the key in `bits` bits.
+    fn hash(&self, bits: u32) -> usize;
 }
 
-#[repr(transparent)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct MatchingBytesKey(u32);
+/// Key of up to 8 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchingBytesKey {
+    value: u64,
+    mask: u64,
+}
 
 impl MatchingKey for MatchingBytesKey {
     type ElementType = u8;
-    type KeyType = u32;
 
     #[inline]
     fn null() -> Self {
-        Self(Default::default())
+        Self { value: 0, mask: 0 }
     }
 
     #[inline]
-    fn new(val0: Self::ElementType, val1: Self::ElementType, val2: Self::ElementType) -> Self {
-        Self(((val0 as u32) << 16) | ((val1 as u32) << 8) | (val2 as u32))
+    fn new(values: &[Self::ElementType]) -> Self {
+        let value = values
+            .iter()
+            .fold(0, |acc, &value| (acc << 8) | value as u64);
+        let mask = u64::MAX >> (64 - 8 * values.len());
+        Self { value, mask }
     }
 
     #[inline]
-    fn key_value(&self) -> Self::KeyType {
-        self.0
+    fn advance(&mut self, new_value: Self::ElementType) {
+        self.value = ((self.value << 8) | (new_value as u64)) & self.mask;
     }
 
     #[inline]
-    fn advance(&mut self, new_value: Self::ElementType) {
-        self.0 = ((self.0 << 8) | (new_value as u32)) & 0xFF_FF_FF;
+    fn hash(&self, bits: u32) -> usize {
+        (self.value.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - bits)) as usize
     }
 }
 
-pub struct OffsetList {
-    root: ManuallyDrop<Box<SimpleList<u32>>>,
+/// Key of elements wider than a byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchingElementsKey<T> {
+    values: [T; MAX_KEY_LEN],
+    len: usize,
 }
 
-#[allow(dead_code)]
-impl OffsetList {
+impl<T> MatchingKey for MatchingElementsKey<T>
+where
+    T: Copy + Default + Into<u64>,
+{
+    type ElementType = T;
+
     #[inline]
-    pub fn new(value: u32) -> Self {
+    fn null() -> Self {
         Self {
-            root: ManuallyDrop::new(SimpleList::new_leaf(value)),
-        }
-    }
-
-    pub fn push(&mut self, value: u32) {
-        unsafe {
-            let next = ManuallyDrop::take(&mut self.root);
-            self.root = ManuallyDrop::new(SimpleList::new(value, next));
+            values: Default::default(),
+            len: 0,
         }
     }
 
     #[inline]
-    pub fn root<'a>(&'a self) -> &'a Box<SimpleList<u32>> {
-        &self.root
+    fn new(values: &[Self::ElementType]) -> Self {
+        let mut key = Self::null();
+        key.values[..values.len()].copy_from_slice(values);
+        key.len = values.len();
+        key
     }
 
     #[inline]
-    #[track_caller]
-    pub fn nearest(&self) -> u32 {
-        self.root.value()
-    }
-
-    pub fn retain(&mut self, min_value: u32) -> bool {
-        if self.nearest() < min_value {
-            return false;
-        }
-        let mut node = (&mut self.root) as &mut Box<SimpleList<u32>>;
-        loop {
-            if let Some(next) = node.next() {
-                if next.value() < min_value {
-                    node.unlink();
-                    break;
-                }
-            }
-            match node.next_mut() {
-                Some(next) => {
-                    node = next;
-                }
-                None => break,
-            }
-        }
-        true
+    fn advance(&mut self, new_value: Self::ElementType) {
+        self.values.copy_within(1..self.len, 0);
+        self.values[self.len - 1] = new_value;
     }
 
     #[inline]
-    pub fn distance_iter<'a>(
-        &'a self,
-        current: usize,
-        min_value: usize,
-    ) -> impl Iterator<Item = usize> + 'a {
-        DistanceIter {
-            node: Some(&self.root),
-            current,
-            min_value,
-        }
-    }
-}
-
-impl Drop for OffsetList {
-    fn drop(&mut self) {
-        unsafe {
-            ManuallyDrop::drop(&mut self.root);
-        }
+    fn hash(&self, bits: u32) -> usize {
+        const K: u64 = 0x9E37_79B9_7F4A_7C15;
+        let value = self.values[..self.len]
+            .iter()
+            .fold(0, |acc: u64, &value| (acc ^ value.into()).wrapping_mul(K));
+        (value >> (64 - bits)) as usize
     }
 }
 
-pub struct SimpleList<T> {
-    next: Option<Box<Self>>,
-    value: T,
+/// Links from each offset to the previous offset with the same hash, in a ring buffer
+///
+/// The ring buffer must not be shorter than the window so that links within the window are never overwritten.
+pub struct OffsetList<'a> {
+    links: &'a mut [usize],
+    mask: usize,
 }
 
-#[allow(dead_code)]
-impl<T> SimpleList<T> {
-    #[inline]
-    pub fn new(value: T, next: Box<Self>) -> Box<Self> {
-        Box::new(Self {
-            value,
-            next: Some(next),
-        })
-    }
-
-    #[inline]
-    pub fn new_leaf(value: T) -> Box<Self> {
-        Box::new(Self { value, next: None })
-    }
-
-    #[inline]
-    pub fn link(&mut self, next: Box<Self>) {
-        self.next = Some(next);
-    }
-
-    #[inline]
-    pub fn unlink(&mut self) {
-        let next = self.next.take();
-        drop(next);
-    }
-
+impl<'a> OffsetList<'a> {
     #[inline]
-    pub fn next(&self) -> Option<&Box<Self>> {
-        self.next.as_ref()
-    }
-
-    #[inline]
-    pub fn next_mut(&mut self) -> Option<&mut Box<Self>> {
-        self.next.as_mut()
+    pub fn new(links: &'a mut [usize]) -> Self {
+        debug_assert!(links.len().is_power_of_two());
+        let mask = links.len() - 1;
+        Self { links, mask }
     }
+}
 
+impl OffsetList<'_> {
     #[inline]
-    pub fn as_ref(&self) -> &T {
-        &self.value
+    pub fn push(&mut self, value: usize, prev: usize) {
+        self.links[value & self.mask] = prev;
     }
 
     #[inline]
-    pub fn value(&self) -> T
-    where
-        T: Copy,
-    {
-        self.value
-    }
-}
-
-impl<T> Drop for SimpleList<T> {
-    fn drop(&mut self) {
-        let mut next = self.next.take();
-        loop {
-            match next {
-                Some(mut v) => next = v.next.take(),
-                None => break,
-            }
+    pub fn distance_iter(
+        &self,
+        nearest: usize,
+        current: usize,
+        min_value: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        DistanceIter {
+            list: self,
+            node: nearest,
+            current,
+            min_value,
         }
     }
 }
 
 struct DistanceIter<'a> {
-    node: Option<&'a Box<SimpleList<u32>>>,
+    list: &'a OffsetList<'a>,
+    node: usize,
     current: usize,
     min_value: usize,
 }
@@ -286,12 +284,48 @@ impl Iterator for DistanceIter<'_> {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node.take() {
-            self.node = node.next();
-            if (node.value() as usize) >= self.min_value {
-                return Some(self.current - node.value() as usize);
-            }
+        let node = self.node;
+        if node == NONE || node < self.min_value {
+            return None;
+        }
+        self.node = self.list.links[node & self.list.mask];
+        Some(self.current - node)
+    }
+}
+
+#[cfg(all(test, feature = "alloc", target_pointer_width = "64"))]
+mod tests {
+    use super::{OffsetList, NONE};
+
+    #[test]
+    fn positions_beyond_u32() {
+        // Positions across 4GB wrap around a ring buffer shorter than the chain
+        let mut links = [0; 8];
+        let mut list = OffsetList::new(&mut links);
+        let start = 0xFFFF_FFFC;
+        let mut prev = NONE;
+        for position in start..start + 12 {
+            list.push(position, prev);
+            prev = position;
         }
-        None
+        let current = start + 12;
+        let distances = list
+            .distance_iter(prev, current, current - 8)
+            .collect::<Vec<_>>();
+        assert_eq!(distances, (1..=8).collect::<Vec<_>>());
+        let distances = list
+            .distance_iter(prev, current, current - 3)
+            .collect::<Vec<_>>();
+        assert_eq!(distances, [1, 2, 3]);
+
+        // Links before the window are overwritten, but not followed
+        let mut links = [0; 8];
+        let mut list = OffsetList::new(&mut links);
+        list.push(start, NONE);
+        list.push(start + 8, start);
+        assert_eq!(
+            list.distance_iter(start + 8, start + 9, start + 1).count(),
+            1
+        );
     }
 }
@@ -0,0 +1,211 @@
+//! C API
+//!
+//! These functions are exported with unmangled names and declared in `extra/capi/include/stk1.h`.
+//! The `libstk1-capi` crate in `extra/capi` builds them into a shared and a static library.
+//!
+//! `tek1_checkformat` and `tek1_decode` are compatible with the routines in `extra/x86/stk1.asm`,
+//! including their `fastcall` calling convention on 32-bit x86.
+
+use crate::{Configuration, DecodeError, EncodeError, Stk1, Stk1Encoder, Tek1};
+use core::{ffi::c_int, marker::PhantomData, slice};
+
+/// The data is invalid or corrupted
+pub const STK1_ERROR_INVALID_DATA: isize = -1;
+/// The input is too large for the format
+pub const STK1_ERROR_INPUT_TOO_LARGE: isize = -2;
+/// The output buffer is too small
+pub const STK1_ERROR_OUTPUT_TOO_SMALL: isize = -3;
+/// Memory allocation failed
+pub const STK1_ERROR_OUT_OF_MEMORY: isize = -4;
+/// A pointer is null or the level is out of range
+pub const STK1_ERROR_INVALID_ARGUMENT: isize = -5;
+
+impl EncodeError {
+    #[inline]
+    fn _code(&self) -> isize {
+        match self {
+            Self::InputTooLarge => STK1_ERROR_INPUT_TOO_LARGE,
+            Self::OutputTooSmall | Self::WorkspaceTooSmall => STK1_ERROR_OUTPUT_TOO_SMALL,
+            Self::OutOfMemory => STK1_ERROR_OUT_OF_MEMORY,
+            Self::InvalidPath => STK1_ERROR_INVALID_ARGUMENT,
+        }
+    }
+}
+
+impl DecodeError {
+    #[inline]
+    fn _code(&self) -> isize {
+        match self {
+            Self::InvalidData => STK1_ERROR_INVALID_DATA,
+            Self::OutOfMemory => STK1_ERROR_OUT_OF_MEMORY,
+        }
+    }
+}
+
+/// Returns the slice, allowing null if `len` is zero.
+#[inline]
+unsafe fn _slice<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// Returns the mutable slice, allowing null if `len` is zero.
+#[inline]
+unsafe fn _slice_mut<'a>(ptr: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Some(&mut []),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts_mut(ptr, len)),
+    }
+}
+
+/// Bytes read from a pointer without a known end, like the assembly version
+struct RawBytes<'a> {
+    ptr: *const u8,
+    _phantom: PhantomData<&'a u8>,
+}
+
+impl<'a> Iterator for RawBytes<'a> {
+    type Item = &'a u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: the caller guarantees that the data is valid up to its last byte
+        let result = unsafe { &*self.ptr };
+        self.ptr = self.ptr.wrapping_add(1);
+        Some(result)
+    }
+}
+
+/// Defines a function with the `fastcall` calling convention on 32-bit x86, or `C` on others.
+macro_rules! fastcall {
+    ($(#[$meta:meta])* pub unsafe fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty $body:block) => {
+        $(#[$meta])*
+        #[cfg(target_arch = "x86")]
+        #[no_mangle]
+        pub unsafe extern "fastcall" fn $name($($arg: $ty),*) -> $ret $body
+
+        $(#[$meta])*
+        #[cfg(not(target_arch = "x86"))]
+        #[no_mangle]
+        pub unsafe extern "C" fn $name($($arg: $ty),*) -> $ret $body
+    };
+}
+
+/// Returns the maximum length of data compressed from `len` bytes.
+#[no_mangle]
+pub extern "C" fn stk1_max_compressed_len(len: usize) -> usize {
+    Stk1::max_compressed_len(len)
+}
+
+/// Compresses `input` into `output` at `level` (1 to 9, or 0 for the default)
+/// and returns the compressed length, or a negative error code.
+///
+/// An output of `stk1_max_compressed_len(input_len)` bytes is always large enough.
+///
+/// # Safety
+///
+/// `input` and `output` must be valid for `input_len` and `output_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn stk1_encode(
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_len: usize,
+    level: c_int,
+) -> isize {
+    let (Some(input), Some(output)) = (_slice(input, input_len), _slice_mut(output, output_len))
+    else {
+        return STK1_ERROR_INVALID_ARGUMENT;
+    };
+    let config = match level {
+        0 => Configuration::DEFAULT,
+        1..=9 => Configuration::DEFAULT.level(level as u32),
+        _ => return STK1_ERROR_INVALID_ARGUMENT,
+    };
+    match Stk1Encoder::new(config).encode_to_slice(input, output) {
+        Ok(len) => len as isize,
+        Err(e) => e._code(),
+    }
+}
+
+/// Decompresses `input` into `output`, whose length must be the decompressed size,
+/// and returns zero, or a negative error code.
+///
+/// # Safety
+///
+/// `input` and `output` must be valid for `input_len` and `output_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn stk1_decode(
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_len: usize,
+) -> isize {
+    let (Some(input), Some(output)) = (_slice(input, input_len), _slice_mut(output, output_len))
+    else {
+        return STK1_ERROR_INVALID_ARGUMENT;
+    };
+    match Stk1::decode(input, output) {
+        Ok(()) => 0,
+        Err(e) => e._code(),
+    }
+}
+
+fastcall! {
+    /// Returns the decompressed size of the tek1 data of `size` bytes at `p`,
+    /// -2 if the signature differs only in the first byte, which marks other variants of tek,
+    /// or -1 if the data is not tek1 or the header is invalid.
+    ///
+    /// # Safety
+    ///
+    /// `p` must be valid for `size` bytes.
+    pub unsafe fn tek1_checkformat(size: c_int, p: *const u8) -> c_int {
+        if size < 17 || p.is_null() {
+            return -1;
+        }
+        let input = slice::from_raw_parts(p, size as usize);
+        // Like `stk1.asm`, the first byte is compared last
+        if input[1..Tek1::SIGNATURE.len()] != Tek1::SIGNATURE[1..] {
+            return -1;
+        }
+        if input[0] != Tek1::SIGNATURE[0] {
+            return -2;
+        }
+        match Tek1::read_header(input) {
+            Ok((size, _)) => c_int::try_from(size).unwrap_or(-1),
+            Err(_) => -1,
+        }
+    }
+}
+
+fastcall! {
+    /// Decompresses the tek1 data at `p` into `q` and returns zero, or 1 if the data is invalid.
+    ///
+    /// # Safety
+    ///
+    /// As the length of the data is not given, the data at `p` must be valid up to its last token.
+    /// Use `stk1_decode` for untrusted data. `q` must be valid for the decompressed size.
+    pub unsafe fn tek1_decode(p: *const u8, q: *mut u8) -> c_int {
+        if p.is_null() {
+            return 1;
+        }
+        let mut input = RawBytes {
+            ptr: p,
+            _phantom: PhantomData,
+        };
+        let Ok(header) = Tek1::_parse_header(&mut input) else {
+            return 1;
+        };
+        let Some(output) = _slice_mut(q, header.size) else {
+            return 1;
+        };
+        match Stk1::_decode(&mut input, output) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        }
+    }
+}
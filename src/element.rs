@@ -0,0 +1,164 @@
+//! Element types of the data to be compressed
+
+use crate::{
+    cache::{MatchingBytesKey, MatchingElementsKey, MatchingKey},
+    lz,
+    sink::Sink,
+    EncodeError,
+};
+
+/// Element types that [`Stk1`](crate::Stk1) can compress
+///
+/// Distances and lengths are counted in elements, and literals are stored in little endian.
+pub trait Element: Copy + PartialEq + Default + private::Sealed {
+    /// Number of bytes of the element
+    const SIZE: usize;
+
+    /// Reads an element from the first [`Element::SIZE`] bytes of the slice.
+    fn from_le_slice(bytes: &[u8]) -> Self;
+}
+
+pub(crate) mod private {
+    use super::*;
+
+    pub trait Sealed: Sized {
+        type Key: MatchingKey<ElementType = Self>;
+
+        fn matching_len(data: &[Self], current: usize, distance: usize, max_len: usize) -> usize;
+
+        fn write_literals<S: Sink>(output: &mut S, literals: &[Self]) -> Result<(), EncodeError>;
+
+        /// Reads a literal, or returns `None` at the end of the input.
+        fn read_literal<'a, I: Iterator<Item = &'a u8>>(iter: &mut I) -> Option<Self>;
+    }
+}
+
+impl Element for u8 {
+    const SIZE: usize = 1;
+
+    #[inline]
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+}
+
+impl private::Sealed for u8 {
+    type Key = MatchingBytesKey;
+
+    #[inline]
+    fn matching_len(data: &[Self], current: usize, distance: usize, max_len: usize) -> usize {
+        lz::matching_len_bytes(data, current, distance, max_len)
+    }
+
+    #[inline]
+    fn write_literals<S: Sink>(output: &mut S, literals: &[Self]) -> Result<(), EncodeError> {
+        output.extend_from_slice(literals)
+    }
+
+    #[inline]
+    fn read_literal<'a, I: Iterator<Item = &'a u8>>(iter: &mut I) -> Option<Self> {
+        iter.next().copied()
+    }
+}
+
+macro_rules! impl_element {
+    ($($ty:ty),*) => {
+        $(
+            impl Element for $ty {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+
+                #[inline]
+                fn from_le_slice(bytes: &[u8]) -> Self {
+                    let mut buf = [0; Self::SIZE];
+                    buf.copy_from_slice(&bytes[..Self::SIZE]);
+                    Self::from_le_bytes(buf)
+                }
+            }
+
+            impl private::Sealed for $ty {
+                type Key = MatchingElementsKey<$ty>;
+
+                #[inline]
+                fn matching_len(data: &[Self], current: usize, distance: usize, max_len: usize) -> usize {
+                    lz::matching_len(data, current, distance, max_len)
+                }
+
+                #[inline]
+                fn write_literals<S: Sink>(output: &mut S, literals: &[Self]) -> Result<(), EncodeError> {
+                    for literal in literals {
+                        output.extend_from_slice(&literal.to_le_bytes())?;
+                    }
+                    Ok(())
+                }
+
+                #[inline]
+                fn read_literal<'a, I: Iterator<Item = &'a u8>>(iter: &mut I) -> Option<Self> {
+                    let mut buf = [0; Self::SIZE];
+                    for p in buf.iter_mut() {
+                        *p = *iter.next()?;
+                    }
+                    Some(Self::from_le_bytes(buf))
+                }
+            }
+        )*
+    };
+}
+
+impl_element!(u16, u32);
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{Configuration, Element, Stk1};
+
+    fn roundtrip<T: Element + core::fmt::Debug>(input: &[T]) -> usize {
+        let encoded = Stk1::encode_elements(input, Configuration::DEFAULT).unwrap();
+        let mut decoded = vec![T::default(); input.len()];
+        Stk1::decode_elements(&encoded, &mut decoded).unwrap();
+        assert_eq!(decoded, input);
+        encoded.len()
+    }
+
+    #[test]
+    fn elements() {
+        let mut seed = 0x2545_F491u32;
+        let mut tiles = Vec::new();
+        for _ in 0..0x4000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            if (seed & 7) < 5 && tiles.len() > 64 {
+                tiles.push(tiles[tiles.len() - 1 - (seed >> 26) as usize]);
+            } else {
+                tiles.push(seed);
+            }
+        }
+
+        let palette = tiles.iter().map(|v| v & 0xFF_FF_FF).collect::<Vec<_>>();
+        assert!(roundtrip(&palette) < palette.len() * 4);
+
+        let tiles = tiles.iter().map(|v| *v as u16).collect::<Vec<_>>();
+        assert!(roundtrip(&tiles) < tiles.len() * 2);
+
+        let bytes = tiles.iter().map(|v| *v as u8).collect::<Vec<_>>();
+        roundtrip(&bytes);
+        assert_eq!(
+            Stk1::encode_elements(&bytes, Configuration::DEFAULT).unwrap(),
+            Stk1::encode(&bytes, Configuration::DEFAULT).unwrap()
+        );
+
+        // The same decoder as `decode` for truncated data
+        let encoded = Stk1::encode(&bytes, Configuration::DEFAULT).unwrap();
+        for len in (0..encoded.len()).step_by(61) {
+            let mut expected = vec![0; bytes.len()];
+            let mut decoded = vec![0; bytes.len()];
+            assert_eq!(
+                Stk1::decode(&encoded[..len], &mut expected).is_ok(),
+                Stk1::decode_elements(&encoded[..len], &mut decoded).is_ok()
+            );
+            assert_eq!(decoded, expected);
+        }
+
+        roundtrip::<u32>(&[]);
+        roundtrip::<u16>(&[1]);
+    }
+}
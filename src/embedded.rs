@@ -0,0 +1,81 @@
+//! Data embedded at compile time
+
+use crate::{DecodeError, Stk1};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Stk1 data embedded in the program with its decompressed length
+///
+/// This is the value of `include_stk1!` in the `libstk1-macros` crate.
+/// Since [`Stk1Embedded::len`] is a `const fn`, a constant of this type can size a static buffer:
+///
+/// ```
+/// use libstk1::Stk1Embedded;
+///
+/// // The value of `include_stk1!("table.bin")`
+/// const TABLE: Stk1Embedded = Stk1Embedded::new(&[0x11, 0x41, 0x21], 4);
+///
+/// let mut buffer = [0; TABLE.len()];
+/// TABLE.decode(&mut buffer).unwrap();
+/// assert_eq!(&buffer, b"AAAA");
+///
+/// // Decoded at compile time
+/// static DECODED: [u8; TABLE.len()] = TABLE.decode_const();
+/// assert_eq!(&DECODED, b"AAAA");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stk1Embedded {
+    data: &'static [u8],
+    len: usize,
+}
+
+impl Stk1Embedded {
+    #[inline]
+    pub const fn new(data: &'static [u8], len: usize) -> Self {
+        Self { data, len }
+    }
+
+    /// Returns the compressed data.
+    #[inline]
+    pub const fn data(&self) -> &'static [u8] {
+        self.data
+    }
+
+    /// Returns the decompressed length.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Decodes the data into `output`, whose length must be the decompressed length.
+    #[inline]
+    pub fn decode(&self, output: &mut [u8]) -> Result<(), DecodeError> {
+        if output.len() != self.len {
+            return Err(DecodeError::InvalidData);
+        }
+        Stk1::decode(self.data, output)
+    }
+
+    /// Decodes the data into an array in const context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is not the decompressed length or the data is invalid.
+    #[inline]
+    pub const fn decode_const<const N: usize>(&self) -> [u8; N] {
+        assert!(N == self.len, "length mismatch");
+        Stk1::decode_const(self.data)
+    }
+
+    /// Decodes the data into a new vector.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn decode_to_vec(&self) -> Result<Vec<u8>, DecodeError> {
+        Stk1::decode_to_vec(self.data, self.len)
+    }
+}
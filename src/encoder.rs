@@ -0,0 +1,354 @@
+//! Reusable encoder context
+
+#[cfg(feature = "alloc")]
+use crate::{cache::MatchingCache, split::PushSplit, Element, EncodeStats};
+use crate::{
+    cache::OffsetCache,
+    sink::{Sink, SliceSink},
+    Configuration, EncodeError, Stk1,
+};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Stk1 encoder that reuses its match finder tables across calls
+///
+/// The tables grow to fit the largest input encoded so far and are not released until the encoder is dropped.
+/// Allocation failures are reported as [`EncodeError::OutOfMemory`] instead of aborting.
+#[cfg(feature = "alloc")]
+pub struct Stk1Encoder {
+    config: Configuration,
+    head: Vec<usize>,
+    links: Vec<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl Stk1Encoder {
+    #[inline]
+    pub const fn new(config: Configuration) -> Self {
+        Self {
+            config,
+            head: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn config(&self) -> &Configuration {
+        &self.config
+    }
+
+    /// Encodes `input` and appends the result to `output`.
+    #[inline]
+    pub fn encode_into(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.encode_with_split(input, output, None, None)
+    }
+
+    /// Encodes elements and appends the result to `output`.
+    ///
+    /// See [`Stk1::encode_elements`].
+    #[inline]
+    pub fn encode_elements_into<T: Element>(
+        &mut self,
+        input: &[T],
+        output: &mut Vec<u8>,
+    ) -> Result<(), EncodeError> {
+        self.encode_with_split(input, output, None, None)
+    }
+
+    /// Encodes `input` into `output` and returns the number of bytes written.
+    ///
+    /// Fails with [`EncodeError::OutputTooSmall`] without writing past the end of `output`.
+    /// An output of [`Stk1::max_compressed_len`] bytes is always large enough.
+    #[inline]
+    pub fn encode_to_slice(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        let mut sink = SliceSink::new(output);
+        self.encode_with_split(input, &mut sink, None, None)?;
+        Ok(sink.len())
+    }
+
+    pub(crate) fn encode_with_split<T: Element, S: Sink>(
+        &mut self,
+        input: &[T],
+        output: &mut S,
+        split: Option<(PushSplit, usize)>,
+        stats: Option<&mut EncodeStats>,
+    ) -> Result<(), EncodeError> {
+        let output_len = Stk1::max_compressed_len(input.len() * T::SIZE);
+        let config = self
+            .config
+            ._limited(input.len(), output_len)
+            .ok_or(EncodeError::OutOfMemory)?;
+        if config._is_limited() {
+            // Growing the output by doubling could exceed the limit
+            output.reserve(output_len)?;
+        }
+        let (head_len, list_len) = config._table_sizes(input.len());
+        if config._is_limited() {
+            // Tables kept from larger inputs count against the limit
+            Self::_release(&mut self.head, head_len);
+            Self::_release(&mut self.links, list_len);
+        }
+        Self::_grow(&mut self.head, head_len)?;
+        Self::_grow(&mut self.links, list_len)?;
+        let offset_cache = MatchingCache::<T::Key>::new(
+            input,
+            config.max_distance(),
+            config._key_len(),
+            &mut self.head[..head_len],
+            &mut self.links[..list_len],
+        );
+        Stk1::_encode(input, &config, offset_cache, output, split, stats)
+    }
+
+    /// Releases the table if it is larger than `len` entries.
+    fn _release(table: &mut Vec<usize>, len: usize) {
+        if table.capacity() > len {
+            *table = Vec::new();
+        }
+    }
+
+    fn _grow(table: &mut Vec<usize>, len: usize) -> Result<(), EncodeError> {
+        if table.len() < len {
+            table
+                .try_reserve_exact(len - table.len())
+                .map_err(|_| EncodeError::OutOfMemory)?;
+            table.resize(len, 0);
+        }
+        Ok(())
+    }
+}
+
+impl Stk1 {
+    /// Returns the expected memory usage of the encoder in bytes, counting the output at its upper bound.
+    ///
+    /// The input is not counted. If the memory limit of `config` is too small,
+    /// returns the usage with the smallest window, which exceeds the limit.
+    pub fn estimate_encoder_memory(config: Configuration, input_len: usize) -> usize {
+        let config = config
+            ._limited(input_len, Self::max_compressed_len(input_len))
+            .unwrap_or(Configuration::new(1, config.max_len()));
+        let (head_len, list_len) = config._table_sizes(input_len);
+        (head_len + list_len) * size_of::<usize>() + Self::max_compressed_len(input_len)
+    }
+
+    /// Encodes `input` into `output` and returns the number of bytes written.
+    ///
+    /// See [`Stk1Encoder::encode_to_slice`].
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encode_to_slice(
+        input: &[u8],
+        config: Configuration,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        Stk1Encoder::new(config).encode_to_slice(input, output)
+    }
+
+    /// Returns the size of the workspace for [`Stk1::encode_with_workspace`] to use the window of `config`.
+    pub fn workspace_size(config: Configuration) -> usize {
+        let (head_len, list_len) = config._table_sizes(config.max_distance());
+        (head_len + list_len) * size_of::<usize>() + align_of::<usize>() - 1
+    }
+
+    /// Encodes without allocation, using `workspace` for the match finder tables.
+    ///
+    /// If the tables for the window of `config` do not fit in the workspace, they are reduced as with
+    /// [`Configuration::memory_limit`], which is otherwise ignored. The other settings of `config` are used as they are.
+    /// Returns the number of bytes written to `output`.
+    pub fn encode_with_workspace(
+        input: &[u8],
+        config: Configuration,
+        workspace: &mut [u8],
+        output: &mut [u8],
+    ) -> Result<usize, EncodeError> {
+        // SAFETY: any bit pattern is a valid usize
+        let (_, tables, _) = unsafe { workspace.align_to_mut::<usize>() };
+
+        let config = config
+            ._fit_tables(input.len(), tables.len())
+            .ok_or(EncodeError::WorkspaceTooSmall)?;
+        let (head_len, list_len) = config._table_sizes(input.len());
+
+        let (head, links) = tables.split_at_mut(head_len);
+        let links = &mut links[..list_len];
+        let offset_cache =
+            OffsetCache::new(input, config.max_distance(), config._key_len(), head, links);
+        let mut sink = SliceSink::new(output);
+        Self::_encode(input, &config, offset_cache, &mut sink, None, None)?;
+        Ok(sink.len())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{Configuration, EncodeError, Stk1, Stk1Encoder};
+
+    #[test]
+    fn reuse() {
+        let inputs: [&[u8]; 5] = [
+            b"",
+            b"ab",
+            b"abcabcabcabcabcabcabcabc",
+            b"The quick brown fox jumps over the lazy dog. The quick brown fox jumps over the lazy dog.",
+            &[0; 1000],
+        ];
+        let mut encoder = Stk1Encoder::new(Configuration::DEFAULT);
+        let mut output = Vec::new();
+        for _ in 0..2 {
+            for input in inputs {
+                output.clear();
+                encoder.encode_into(input, &mut output).unwrap();
+                assert_eq!(output, Stk1::encode(input, Configuration::DEFAULT).unwrap());
+                assert_eq!(Stk1::decode_to_vec(&output, input.len()).unwrap(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn max_compressed_len() {
+        let mut seed = 0x2545_F491u32;
+        let mut random = Vec::new();
+        for _ in 0..0x10000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            random.push(seed as u8);
+        }
+        // 16 literals followed by a short match
+        let mut groups = Vec::new();
+        for (index, chunk) in random.chunks(16).enumerate() {
+            groups.extend_from_slice(chunk);
+            groups.resize(groups.len() + 2, index as u8);
+        }
+
+        for input in [&random[..], &random[..1], &random[..17], &groups] {
+            for config in [Configuration::TINY, Configuration::DEFAULT] {
+                let bound = Stk1::max_compressed_len(input.len());
+                let encoded = Stk1::encode(input, config).unwrap();
+                assert!(encoded.len() <= bound);
+
+                let mut output = vec![0; bound];
+                let len = Stk1::encode_to_slice(input, config, &mut output).unwrap();
+                assert_eq!(&output[..len], &encoded);
+            }
+        }
+    }
+
+    #[test]
+    fn memory_limit() {
+        let input = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 1000].concat();
+        let unlimited = Stk1::estimate_encoder_memory(Configuration::DEFAULT, input.len());
+        for limit in [unlimited, unlimited / 2, 0x10000, 0xD000] {
+            let config = Configuration::DEFAULT.memory_limit(limit);
+            assert!(Stk1::estimate_encoder_memory(config, input.len()) <= limit);
+            let encoded = Stk1::encode(&input, config).unwrap();
+            assert_eq!(Stk1::decode_to_vec(&encoded, input.len()).unwrap(), input);
+        }
+        assert!(matches!(
+            Stk1::encode(&input, Configuration::DEFAULT.memory_limit(input.len())),
+            Err(EncodeError::OutOfMemory)
+        ));
+    }
+
+    #[test]
+    fn memory_limit_reuse() {
+        let large = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 5000].concat();
+        let small = &large[..0x1000];
+        let limit = Stk1::estimate_encoder_memory(Configuration::DEFAULT, large.len()) / 2;
+        let config = Configuration::DEFAULT.memory_limit(limit);
+        let tables = |encoder: &Stk1Encoder| {
+            (encoder.head.capacity() + encoder.links.capacity()) * size_of::<usize>()
+        };
+
+        let mut encoder = Stk1Encoder::new(config);
+        let mut output = Vec::new();
+        encoder.encode_into(&large, &mut output).unwrap();
+        assert!(tables(&encoder) + output.capacity() <= limit);
+        assert_eq!(Stk1::decode_to_vec(&output, large.len()).unwrap(), large);
+
+        // Tables of the large input are released
+        let mut output = Vec::new();
+        encoder.encode_into(small, &mut output).unwrap();
+        assert!(
+            tables(&encoder) + output.capacity()
+                <= Stk1::estimate_encoder_memory(config, small.len())
+        );
+        assert_eq!(output, Stk1::encode(small, config).unwrap());
+    }
+
+    #[test]
+    fn levels() {
+        let input = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 100].concat();
+        for level in 0..=10 {
+            let config = Configuration::DEFAULT.level(level);
+            let encoded = Stk1::encode(&input, config).unwrap();
+            assert_eq!(Stk1::decode_to_vec(&encoded, input.len()).unwrap(), input);
+        }
+        for key_len in 3..=5 {
+            let config = Configuration::DEFAULT.key_len(key_len);
+            for input in [&input[..key_len], &input[..key_len + 1], &input] {
+                let encoded = Stk1::encode(input, config).unwrap();
+                assert_eq!(Stk1::decode_to_vec(&encoded, input.len()).unwrap(), input);
+            }
+            let elements = input.iter().map(|&v| v as u16).collect::<Vec<_>>();
+            let encoded = Stk1::encode_elements(&elements, config).unwrap();
+            let mut decoded = vec![0u16; elements.len()];
+            Stk1::decode_elements(&encoded, &mut decoded).unwrap();
+            assert_eq!(decoded, elements);
+        }
+        assert_eq!(
+            Stk1::encode(&input, Configuration::DEFAULT.level(6)).unwrap(),
+            Stk1::encode(&input, Configuration::DEFAULT).unwrap()
+        );
+    }
+
+    #[test]
+    fn workspace() {
+        let input = b"The quick brown fox jumps over the lazy dog. The quick brown fox jumps over the lazy dog.";
+        let mut workspace = vec![0; Stk1::workspace_size(Configuration::TINY)];
+        for config in [
+            Configuration::TINY,
+            Configuration::TINY.level(1),
+            Configuration::TINY.level(9).key_len(5),
+        ] {
+            let expected = Stk1::encode(input, config).unwrap();
+            let mut output = vec![0; expected.len()];
+            let len =
+                Stk1::encode_with_workspace(input, config, &mut workspace, &mut output).unwrap();
+            assert_eq!(&output[..len], &expected);
+        }
+
+        // The window of `LARGE` is reduced to fit
+        let input = [input.as_slice(); 1000].concat();
+        let mut output = vec![0; Stk1::max_compressed_len(input.len())];
+        let len =
+            Stk1::encode_with_workspace(&input, Configuration::LARGE, &mut workspace, &mut output)
+                .unwrap();
+        assert_eq!(
+            Stk1::decode_to_vec(&output[..len], input.len()).unwrap(),
+            input
+        );
+
+        let input = &input[..89];
+        let expected = Stk1::encode(input, Configuration::TINY).unwrap();
+        for len in 0..expected.len() {
+            assert!(matches!(
+                Stk1::encode_with_workspace(
+                    input,
+                    Configuration::TINY,
+                    &mut workspace,
+                    &mut output[..len]
+                ),
+                Err(EncodeError::OutputTooSmall)
+            ));
+        }
+        assert!(matches!(
+            Stk1::encode_with_workspace(input, Configuration::TINY, &mut [], &mut output),
+            Err(EncodeError::WorkspaceTooSmall)
+        ));
+    }
+}
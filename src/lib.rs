@@ -3,7 +3,7 @@
 //! # CAUTION
 //!
 //! **THIS LIBRARY IS AN ALPHA VERSION**.
-//! Compression and decompression itself is possible, but you will need to provide your own processing for file headers and data size outside the library.
+//! Compression and decompression itself is possible. Of the file headers, only the tek1 header is supported ([`Tek1`]); for raw stk1 data, you will need to keep the data size outside the library.
 //!
 //! # NOTE
 //!
@@ -12,7 +12,16 @@
 //!
 //! The following _incompatibilities_ exist:
 //! * The various limits are not official values.
-//! * Only compressed data is supported; headers are not.
+//! * Of the headers, only the tek1 header read by `extra/x86/stk1.asm` is supported (see [`Tek1`]).
+//!
+//! # Cargo features
+//!
+//! * `alloc` (default): Encoding into `Vec`, `Stk1Encoder`, `SplitIndex` and `Archive`.
+//!   Without it, [`Stk1::decode`], [`S7s`] and [`Stk1::encode_with_workspace`] are still available.
+//! * `std` (default): `Stk1::decode_parallel`. Implies `alloc`.
+//! * `asm`: The decoder written in x86-64 assembly for Linux, selected by [`Stk1::decode_with`].
+//! * `capi`: C functions in [`capi`](crate::capi), which `extra/capi` builds into a C library. Implies `std`.
+//! * `sfx`: `Sfx`, which generates self-extracting executables for x86-64 Linux. Implies `alloc`.
 //!
 //! # Original specifications
 //!
@@ -22,20 +31,68 @@
 
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(all(feature = "std", not(test)))]
+extern crate std;
 
 mod stk1;
 pub use stk1::*;
 mod s7s;
 pub use s7s::*;
+mod split;
+pub use split::*;
+mod element;
+pub use element::Element;
+mod embedded;
+pub use embedded::*;
+mod stats;
+pub use stats::*;
+mod tek1;
+pub use tek1::*;
+mod token;
+pub use token::*;
+mod encoder;
+#[cfg(feature = "alloc")]
+pub use encoder::*;
+#[cfg(feature = "alloc")]
+mod archive;
+#[cfg(feature = "alloc")]
+pub use archive::*;
+#[cfg(feature = "sfx")]
+mod sfx;
+#[cfg(feature = "sfx")]
+pub use sfx::*;
+#[cfg(feature = "capi")]
+pub mod capi;
 
+#[cfg(all(feature = "asm", target_arch = "x86_64", target_os = "linux"))]
+mod asm;
 mod cache;
 mod lz;
+mod sink;
 mod var_slice;
 
 #[derive(Debug)]
 pub enum EncodeError {
     // InvalidData,
+    InputTooLarge,
+    OutputTooSmall,
+    WorkspaceTooSmall,
+    OutOfMemory,
+    InvalidPath,
+}
+
+impl core::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::InputTooLarge => "input too large for the format",
+            Self::OutputTooSmall => "output buffer too small",
+            Self::WorkspaceTooSmall => "workspace too small",
+            Self::OutOfMemory => "out of memory",
+            Self::InvalidPath => "invalid path for an archive entry",
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -43,3 +100,18 @@ pub enum DecodeError {
     InvalidData,
     OutOfMemory,
 }
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::InvalidData => "invalid or corrupted data",
+            Self::OutOfMemory => "out of memory",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
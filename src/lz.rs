@@ -28,6 +28,52 @@ where
     }
 }
 
+/// Same as [`matching_len`], but compares a word at a time.
+///
+/// The first mismatch in a word is found by counting the zero bits of the difference.
+/// Bytes shorter than a word are compared by [`matching_len`].
+#[inline]
+#[track_caller]
+pub(super) fn matching_len_bytes(
+    data: &[u8],
+    current: usize,
+    distance: usize,
+    max_len: usize,
+) -> usize {
+    const WORD: usize = core::mem::size_of::<usize>();
+    assert!(
+        data.len() > current && distance != 0 && current >= distance,
+        "INVALID MATCHES: LEN {} CURRENT {} DISTANCE {}",
+        data.len(),
+        current,
+        distance
+    );
+    let max_len = (data.len() - current).min(max_len);
+    let mut len = 0;
+    unsafe {
+        let p = data.as_ptr().add(current);
+        let q = data.as_ptr().add(current - distance);
+        while len + WORD <= max_len {
+            let diff = p.add(len).cast::<usize>().read_unaligned()
+                ^ q.add(len).cast::<usize>().read_unaligned();
+            if diff != 0 {
+                let bits = if cfg!(target_endian = "little") {
+                    diff.trailing_zeros()
+                } else {
+                    diff.leading_zeros()
+                };
+                return len + bits as usize / 8;
+            }
+            len += WORD;
+        }
+    }
+    if len < max_len {
+        len + matching_len(data, current + len, distance, max_len - len)
+    } else {
+        max_len
+    }
+}
+
 /// Matching distance and length
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Matches {
@@ -53,3 +99,33 @@ impl Default for Matches {
         Self::ZERO
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{matching_len, matching_len_bytes};
+
+    #[test]
+    fn matching_len_words() {
+        let mut seed = 0x2545_F491u32;
+        let mut data = Vec::new();
+        for _ in 0..0x1000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            // Few distinct values make long and short matches
+            data.push((seed >> 24) as u8 & 3);
+        }
+        data.extend_from_slice(&[0; 100]);
+
+        for current in 1..data.len() {
+            for distance in 1..=current.min(40) {
+                for max_len in [3, 16, usize::MAX] {
+                    assert_eq!(
+                        matching_len_bytes(&data, current, distance, max_len),
+                        matching_len(&data, current, distance, max_len)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
 /// One of the representation formats for multibyte integers
@@ -10,6 +11,39 @@ use alloc::vec::Vec;
 pub struct S7s;
 
 impl S7s {
+    /// Maximum number of bytes of an encoded 64-bit value
+    pub const MAX_LEN: usize = 10;
+
+    /// Encoded zero
+    pub const ZERO: u8 = 0x01;
+
+    /// Returns the number of bytes needed to encode the value.
+    #[inline]
+    pub const fn encoded_len(value: usize) -> usize {
+        let mut value = (value as u64) >> 7;
+        let mut len = 1;
+        while value > 0 {
+            value >>= 7;
+            len += 1;
+        }
+        len
+    }
+
+    /// Writes the value to the beginning of the slice and returns the number of bytes written.
+    ///
+    /// Returns `None` if the slice is too short.
+    pub fn write_to_slice(output: &mut [u8], value: usize) -> Option<usize> {
+        let len = Self::encoded_len(value);
+        let output = output.get_mut(..len)?;
+        let value = value as u64;
+        for (index, p) in output.iter_mut().enumerate() {
+            *p = ((value >> (7 * (len - 1 - index))) as u8) << 1;
+        }
+        output[len - 1] |= 1;
+        Some(len)
+    }
+
+    #[cfg(feature = "alloc")]
     pub fn write(output: &mut Vec<u8>, value: usize) {
         let value = value as u64;
         if value < 0x80 {
@@ -92,6 +126,25 @@ impl S7s {
         Some(acc >> 1)
     }
 
+    /// Reads the value at `index` of `input` in const context, starting with the accumulator `acc`,
+    /// and returns it with the index of the next byte.
+    pub(crate) const fn _read_const(
+        input: &[u8],
+        index: usize,
+        acc: usize,
+    ) -> Option<(usize, usize)> {
+        let mut acc = acc;
+        let mut index = index;
+        while (acc & 1) == 0 {
+            if index >= input.len() {
+                return None;
+            }
+            acc = (acc << 7) | input[index] as usize;
+            index += 1;
+        }
+        Some((acc >> 1, index))
+    }
+
     #[inline]
     pub fn read<'a, T>(iter: &mut T) -> Option<usize>
     where
@@ -101,7 +154,7 @@ impl S7s {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::S7s;
 
@@ -121,6 +174,11 @@ mod tests {
                 let decoded = S7s::read(&mut iter).unwrap();
 
                 assert_eq!(value, decoded);
+
+                let mut buf = [0; S7s::MAX_LEN];
+                let len = S7s::write_to_slice(&mut buf, value).unwrap();
+                assert_eq!(&buf[..len], &vec);
+                assert_eq!(len, S7s::encoded_len(value));
             }
         }
     }
@@ -0,0 +1,212 @@
+//! Self-extracting executable
+
+use crate::{Configuration, EncodeError, Stk1Encoder};
+use alloc::vec::Vec;
+
+/// What the executable does with the decompressed data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SfxMode {
+    /// Write the data to standard output
+    Write,
+    /// Execute the data as a program with the same arguments and environment
+    ///
+    /// This uses `memfd_create` and `execveat`, which require Linux 3.19 or later.
+    Exec,
+}
+
+/// Generator of self-extracting executables for x86-64 Linux
+///
+/// The executable is a static ELF file of a single segment, which consists of the ELF header,
+/// [`Sfx::STUB`] and the compressed data. The stub decodes the data into memory,
+/// then writes or executes it. It exits with status 127 if decoding fails.
+pub struct Sfx;
+
+impl Sfx {
+    /// Decoder stub assembled from `extra/x86/sfx64.s`, ending with the parameters
+    #[rustfmt::skip]
+    pub const STUB: [u8; 456] = [
+        0x49, 0x89, 0xE4, 0x48, 0x8B, 0x35, 0xA6, 0x01, 0x00, 0x00, 0x48, 0xFF, 0xC6, 0x31, 0xFF, 0xBA,
+        0x03, 0x00, 0x00, 0x00, 0x41, 0xBA, 0x22, 0x00, 0x00, 0x00, 0x49, 0x83, 0xC8, 0xFF, 0x45, 0x31,
+        0xC9, 0xB8, 0x09, 0x00, 0x00, 0x00, 0x0F, 0x05, 0x48, 0x3D, 0x00, 0xF0, 0xFF, 0xFF, 0x0F, 0x87,
+        0x45, 0x01, 0x00, 0x00, 0x48, 0x89, 0xC3, 0x48, 0x89, 0xC7, 0x49, 0x89, 0xC5, 0x4C, 0x03, 0x2D,
+        0x6C, 0x01, 0x00, 0x00, 0x48, 0x8D, 0x35, 0x7D, 0x01, 0x00, 0x00, 0x49, 0x89, 0xF6, 0x4C, 0x03,
+        0x35, 0x63, 0x01, 0x00, 0x00, 0x4C, 0x39, 0xEF, 0x0F, 0x83, 0xBF, 0x00, 0x00, 0x00, 0xE8, 0x93,
+        0x00, 0x00, 0x00, 0x89, 0xC5, 0x83, 0xE0, 0x0F, 0x75, 0x05, 0xE8, 0x93, 0x00, 0x00, 0x00, 0x49,
+        0x89, 0xC7, 0x89, 0xE8, 0xC1, 0xE8, 0x04, 0x75, 0x05, 0xE8, 0x84, 0x00, 0x00, 0x00, 0x48, 0x89,
+        0xC5, 0x4D, 0x85, 0xFF, 0x74, 0x14, 0x4C, 0x39, 0xF6, 0x73, 0x0F, 0x4C, 0x39, 0xEF, 0x0F, 0x83,
+        0xE5, 0x00, 0x00, 0x00, 0xA4, 0x49, 0xFF, 0xCF, 0xEB, 0xE7, 0x4C, 0x39, 0xEF, 0x73, 0x7E, 0x48,
+        0x85, 0xED, 0x74, 0xB1, 0xE8, 0x4D, 0x00, 0x00, 0x00, 0x49, 0x89, 0xC7, 0x83, 0xE0, 0x0F, 0xE8,
+        0x50, 0x00, 0x00, 0x00, 0x48, 0x8D, 0x48, 0x01, 0x44, 0x89, 0xF8, 0xC1, 0xE8, 0x04, 0x75, 0x05,
+        0xE8, 0x3D, 0x00, 0x00, 0x00, 0x48, 0xFF, 0xC0, 0x48, 0x89, 0xFA, 0x48, 0x29, 0xDA, 0x48, 0x39,
+        0xD1, 0x0F, 0x87, 0xA2, 0x00, 0x00, 0x00, 0x4C, 0x89, 0xEA, 0x48, 0x29, 0xFA, 0x48, 0x39, 0xD0,
+        0x48, 0x0F, 0x47, 0xC2, 0x56, 0x48, 0x89, 0xFE, 0x48, 0x29, 0xCE, 0x48, 0x89, 0xC1, 0xF3, 0xA4,
+        0x5E, 0x48, 0xFF, 0xCD, 0xEB, 0xA9, 0x4C, 0x39, 0xF6, 0x73, 0x7E, 0x0F, 0xB6, 0x06, 0x48, 0xFF,
+        0xC6, 0xC3, 0x31, 0xC0, 0xA8, 0x01, 0x75, 0x11, 0x48, 0x89, 0xC2, 0xE8, 0xE6, 0xFF, 0xFF, 0xFF,
+        0x48, 0xC1, 0xE2, 0x07, 0x48, 0x09, 0xD0, 0xEB, 0xEB, 0x48, 0xD1, 0xE8, 0xC3, 0x48, 0x83, 0x3D,
+        0x9B, 0x00, 0x00, 0x00, 0x00, 0x75, 0x0E, 0xBF, 0x01, 0x00, 0x00, 0x00, 0xE8, 0x54, 0x00, 0x00,
+        0x00, 0x31, 0xFF, 0xEB, 0x49, 0x48, 0x8D, 0x3D, 0x6D, 0x00, 0x00, 0x00, 0x31, 0xF6, 0xB8, 0x3F,
+        0x01, 0x00, 0x00, 0x0F, 0x05, 0x48, 0x85, 0xC0, 0x78, 0x2F, 0x49, 0x89, 0xC7, 0x89, 0xC7, 0xE8,
+        0x31, 0x00, 0x00, 0x00, 0x44, 0x89, 0xFF, 0x48, 0x8D, 0x35, 0x4E, 0x00, 0x00, 0x00, 0x49, 0x8D,
+        0x54, 0x24, 0x08, 0x4D, 0x8B, 0x14, 0x24, 0x4E, 0x8D, 0x54, 0xD2, 0x08, 0x41, 0xB8, 0x00, 0x10,
+        0x00, 0x00, 0xB8, 0x42, 0x01, 0x00, 0x00, 0x0F, 0x05, 0xBF, 0x7F, 0x00, 0x00, 0x00, 0xB8, 0xE7,
+        0x00, 0x00, 0x00, 0x0F, 0x05, 0x48, 0x89, 0xDE, 0x48, 0x8B, 0x15, 0x21, 0x00, 0x00, 0x00, 0x48,
+        0x85, 0xD2, 0x74, 0x14, 0xB8, 0x01, 0x00, 0x00, 0x00, 0x0F, 0x05, 0x48, 0x85, 0xC0, 0x7E, 0xD9,
+        0x48, 0x01, 0xC6, 0x48, 0x29, 0xC2, 0xEB, 0xE7, 0xC3, 0x73, 0x66, 0x78, 0x00, 0x0F, 0x1F, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Virtual address of the segment
+    const BASE: u64 = 0x40_0000;
+    const EHDR_LEN: usize = 64;
+    const PHDR_LEN: usize = 56;
+    /// Offset of the stub in the file
+    const STUB_OFFSET: usize = Self::EHDR_LEN + Self::PHDR_LEN;
+    /// Offset of the parameters (size, compressed length and mode) in the stub
+    const PARAMS_OFFSET: usize = Self::STUB.len() - 24;
+
+    /// Returns the length of the executable excluding the compressed data.
+    #[inline]
+    pub const fn overhead() -> usize {
+        Self::STUB_OFFSET + Self::STUB.len()
+    }
+
+    /// Encodes `input` and returns an executable that restores it.
+    pub fn build(
+        input: &[u8],
+        config: Configuration,
+        mode: SfxMode,
+    ) -> Result<Vec<u8>, EncodeError> {
+        let mut output = Vec::new();
+        output
+            .try_reserve(Self::overhead())
+            .map_err(|_| EncodeError::OutOfMemory)?;
+        output.resize(Self::overhead(), 0);
+        Stk1Encoder::new(config).encode_into(input, &mut output)?;
+        let compressed_len = output.len() - Self::overhead();
+        let file_len = output.len() as u64;
+
+        let ehdr = &mut output[..Self::EHDR_LEN];
+        ehdr[..8].copy_from_slice(&[0x7F, b'E', b'L', b'F', 2, 1, 1, 0]);
+        Self::_put(ehdr, 16, &2u16.to_le_bytes()); // ET_EXEC
+        Self::_put(ehdr, 18, &0x3Eu16.to_le_bytes()); // EM_X86_64
+        Self::_put(ehdr, 20, &1u32.to_le_bytes());
+        Self::_put(
+            ehdr,
+            24,
+            &(Self::BASE + Self::STUB_OFFSET as u64).to_le_bytes(),
+        );
+        Self::_put(ehdr, 32, &(Self::EHDR_LEN as u64).to_le_bytes());
+        Self::_put(ehdr, 52, &(Self::EHDR_LEN as u16).to_le_bytes());
+        Self::_put(ehdr, 54, &(Self::PHDR_LEN as u16).to_le_bytes());
+        Self::_put(ehdr, 56, &1u16.to_le_bytes());
+
+        let phdr = &mut output[Self::EHDR_LEN..Self::STUB_OFFSET];
+        Self::_put(phdr, 0, &1u32.to_le_bytes()); // PT_LOAD
+        Self::_put(phdr, 4, &5u32.to_le_bytes()); // PF_R | PF_X
+        Self::_put(phdr, 16, &Self::BASE.to_le_bytes());
+        Self::_put(phdr, 24, &Self::BASE.to_le_bytes());
+        Self::_put(phdr, 32, &file_len.to_le_bytes());
+        Self::_put(phdr, 40, &file_len.to_le_bytes());
+        Self::_put(phdr, 48, &0x1000u64.to_le_bytes());
+
+        let stub = &mut output[Self::STUB_OFFSET..Self::overhead()];
+        stub.copy_from_slice(&Self::STUB);
+        let params = &mut stub[Self::PARAMS_OFFSET..];
+        Self::_put(params, 0, &(input.len() as u64).to_le_bytes());
+        Self::_put(params, 8, &(compressed_len as u64).to_le_bytes());
+        Self::_put(params, 16, &((mode == SfxMode::Exec) as u64).to_le_bytes());
+        Ok(output)
+    }
+
+    #[inline]
+    fn _put(output: &mut [u8], offset: usize, value: &[u8]) {
+        output[offset..offset + value.len()].copy_from_slice(value);
+    }
+}
+
+#[cfg(all(test, target_os = "linux", target_arch = "x86_64"))]
+mod tests {
+    use crate::{Configuration, Sfx, SfxMode, Stk1};
+    use std::{
+        env, fs,
+        os::unix::fs::PermissionsExt,
+        path::PathBuf,
+        process::{Command, Output},
+    };
+
+    fn run(name: &str, executable: &[u8], args: &[&str]) -> Output {
+        let path = env::temp_dir().join(format!("libstk1-sfx-{}-{}", name, std::process::id()));
+        fs::write(&path, executable).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        let output = Command::new(&path).args(args).output().unwrap();
+        fs::remove_file(&path).unwrap();
+        output
+    }
+
+    #[test]
+    fn write() {
+        let mut random = Vec::new();
+        let mut seed = 1u32;
+        for _ in 0..100_000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            random.push((seed >> 16) as u8);
+        }
+        let text = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 1000].concat();
+        let source =
+            fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/stk1.rs")).unwrap();
+        let inputs: [(&str, &[u8]); 6] = [
+            ("empty", b""),
+            ("byte", b"a"),
+            ("zeros", &[0; 70_000]),
+            ("random", &random),
+            ("text", &text),
+            ("source", &source),
+        ];
+        for (name, input) in inputs {
+            for config in [Configuration::TINY.level(1), Configuration::DEFAULT] {
+                let executable = Sfx::build(input, config, SfxMode::Write).unwrap();
+                assert!(
+                    executable.len() <= Sfx::overhead() + Stk1::max_compressed_len(input.len())
+                );
+                let output = run(name, &executable, &[]);
+                assert_eq!(output.status.code(), Some(0), "{}", name);
+                assert!(output.stdout == input, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let input = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 100].concat();
+        let mut executable = Sfx::build(&input, Configuration::DEFAULT, SfxMode::Write).unwrap();
+        // Truncated data
+        let len = executable.len();
+        executable.truncate(len - 1);
+        let phdr_filesz = Sfx::EHDR_LEN + 32;
+        for offset in [phdr_filesz, phdr_filesz + 8] {
+            executable[offset..offset + 8].copy_from_slice(&(len as u64 - 1).to_le_bytes());
+        }
+        let params = Sfx::STUB_OFFSET + Sfx::PARAMS_OFFSET;
+        let compressed_len = (len - 1 - Sfx::overhead()) as u64;
+        executable[params + 8..params + 16].copy_from_slice(&compressed_len.to_le_bytes());
+        assert_eq!(run("invalid", &executable, &[]).status.code(), Some(127));
+    }
+
+    #[test]
+    fn exec() {
+        let script = b"#!/bin/sh\necho \"$# $1 $2 $SFX_TEST\"\nexit 3\n";
+        let executable = Sfx::build(script, Configuration::DEFAULT, SfxMode::Exec).unwrap();
+        let path = env::temp_dir().join(format!("libstk1-sfx-exec-{}", std::process::id()));
+        fs::write(&path, &executable).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        let output = Command::new(&path)
+            .args(["a", "b c"])
+            .env("SFX_TEST", "env")
+            .output()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"2 a b c env\n");
+    }
+}
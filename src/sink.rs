@@ -0,0 +1,124 @@
+//! Destinations of the encoded data
+
+use crate::{EncodeError, S7s};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+pub trait Sink {
+    fn len(&self) -> usize;
+
+    fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), EncodeError>;
+
+    /// Inserts `data` at `index`, moving the bytes after it.
+    fn insert(&mut self, index: usize, data: &[u8]) -> Result<(), EncodeError>;
+
+    fn get_mut(&mut self, index: usize) -> &mut u8;
+
+    /// Reserves capacity for at least `additional` more bytes.
+    #[inline]
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    fn reserve(&mut self, additional: usize) -> Result<(), EncodeError> {
+        let _ = additional;
+        Ok(())
+    }
+
+    #[inline]
+    fn push(&mut self, value: u8) -> Result<(), EncodeError> {
+        self.extend_from_slice(&[value])
+    }
+
+    #[inline]
+    fn write_s7s(&mut self, value: usize) -> Result<(), EncodeError> {
+        let mut buf = [0; S7s::MAX_LEN];
+        let len = S7s::write_to_slice(&mut buf, value).unwrap();
+        self.extend_from_slice(&buf[..len])
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Sink for Vec<u8> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), EncodeError> {
+        self.try_reserve(data.len())
+            .map_err(|_| EncodeError::OutOfMemory)?;
+        Vec::extend_from_slice(self, data);
+        Ok(())
+    }
+
+    #[inline]
+    fn insert(&mut self, index: usize, data: &[u8]) -> Result<(), EncodeError> {
+        Sink::extend_from_slice(self, data)?;
+        self[index..].rotate_right(data.len());
+        Ok(())
+    }
+
+    #[inline]
+    fn get_mut(&mut self, index: usize) -> &mut u8 {
+        &mut self[index]
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) -> Result<(), EncodeError> {
+        self.try_reserve_exact(additional)
+            .map_err(|_| EncodeError::OutOfMemory)
+    }
+
+    #[inline]
+    fn push(&mut self, value: u8) -> Result<(), EncodeError> {
+        self.try_reserve(1).map_err(|_| EncodeError::OutOfMemory)?;
+        Vec::push(self, value);
+        Ok(())
+    }
+}
+
+/// Fixed size destination that never writes past the end of the slice
+pub(crate) struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    #[inline]
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+}
+
+impl Sink for SliceSink<'_> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), EncodeError> {
+        let new_len = self.len + data.len();
+        self.buf
+            .get_mut(self.len..new_len)
+            .ok_or(EncodeError::OutputTooSmall)?
+            .copy_from_slice(data);
+        self.len = new_len;
+        Ok(())
+    }
+
+    fn insert(&mut self, index: usize, data: &[u8]) -> Result<(), EncodeError> {
+        let new_len = self.len + data.len();
+        if new_len > self.buf.len() {
+            return Err(EncodeError::OutputTooSmall);
+        }
+        self.buf.copy_within(index..self.len, index + data.len());
+        self.buf[index..index + data.len()].copy_from_slice(data);
+        self.len = new_len;
+        Ok(())
+    }
+
+    #[inline]
+    fn get_mut(&mut self, index: usize) -> &mut u8 {
+        &mut self.buf[..self.len][index]
+    }
+}
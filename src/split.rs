@@ -0,0 +1,295 @@
+//! Split points for parallel decoding
+
+use crate::EncodeError;
+#[cfg(feature = "alloc")]
+use crate::{DecodeError, S7s};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// A position in the stream where a new group begins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitPoint {
+    /// Offset in the compressed data
+    pub compressed: usize,
+    /// Offset in the decompressed data
+    pub decompressed: usize,
+}
+
+/// Receives split points from the encoder
+pub(crate) type PushSplit<'a> = &'a mut dyn FnMut(SplitPoint) -> Result<(), EncodeError>;
+
+/// Side index of split points, generated by [`Stk1::encode_with_index`](crate::Stk1::encode_with_index)
+///
+/// The index is not a part of the compressed data and must be stored separately.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SplitIndex {
+    points: Vec<SplitPoint>,
+}
+
+#[cfg(feature = "alloc")]
+impl SplitIndex {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { points: Vec::new() }
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[SplitPoint] {
+        &self.points
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn try_push(&mut self, point: SplitPoint) -> Result<(), EncodeError> {
+        self.points
+            .try_reserve(1)
+            .map_err(|_| EncodeError::OutOfMemory)?;
+        self.points.push(point);
+        Ok(())
+    }
+
+    /// Writes the index as a sequence of S7s deltas.
+    pub fn write(&self, output: &mut Vec<u8>) {
+        S7s::write(output, self.points.len());
+        let mut last = SplitPoint {
+            compressed: 0,
+            decompressed: 0,
+        };
+        for point in self.points.iter() {
+            S7s::write(output, point.compressed - last.compressed);
+            S7s::write(output, point.decompressed - last.decompressed);
+            last = *point;
+        }
+    }
+
+    /// Reads the index written by [`SplitIndex::write`].
+    pub fn read(input: &[u8]) -> Result<Self, DecodeError> {
+        let mut iter = input.iter();
+        let iter = &mut iter;
+        let count = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+        let mut points = Vec::new();
+        points
+            .try_reserve_exact(count.min(input.len()))
+            .map_err(|_| DecodeError::OutOfMemory)?;
+        let mut last = SplitPoint {
+            compressed: 0,
+            decompressed: 0,
+        };
+        for _ in 0..count {
+            let compressed = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+            let decompressed = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+            last = SplitPoint {
+                compressed: last
+                    .compressed
+                    .checked_add(compressed)
+                    .ok_or(DecodeError::InvalidData)?,
+                decompressed: last
+                    .decompressed
+                    .checked_add(decompressed)
+                    .ok_or(DecodeError::InvalidData)?,
+            };
+            points.push(last);
+        }
+        Ok(Self { points })
+    }
+}
+
+#[cfg(feature = "std")]
+mod parallel {
+    use super::*;
+    use crate::Stk1;
+    use std::{panic, thread};
+
+    /// A match whose source was not yet available in the first pass
+    struct Deferred {
+        cursor: usize,
+        distance: usize,
+        len: usize,
+    }
+
+    struct Segment<'a> {
+        input: &'a [u8],
+        base: usize,
+        output: &'a mut [u8],
+        is_last: bool,
+    }
+
+    impl Stk1 {
+        /// Decodes segments delimited by `index` on multiple threads.
+        ///
+        /// Literals and matches within each segment are decoded in parallel,
+        /// then matches referring to data before their segment are resolved in order.
+        /// The result is the same as [`Stk1::decode`].
+        pub fn decode_parallel(
+            input: &[u8],
+            index: &SplitIndex,
+            output: &mut [u8],
+        ) -> Result<(), DecodeError> {
+            let mut segments = Vec::new();
+            segments
+                .try_reserve_exact(index.len() + 1)
+                .map_err(|_| DecodeError::OutOfMemory)?;
+            {
+                let mut input_rest = input;
+                let mut output_rest = &mut *output;
+                let mut last = SplitPoint {
+                    compressed: 0,
+                    decompressed: 0,
+                };
+                for point in index.points() {
+                    if point.compressed < last.compressed
+                        || point.decompressed < last.decompressed
+                        || point.compressed - last.compressed > input_rest.len()
+                        || point.decompressed - last.decompressed > output_rest.len()
+                    {
+                        return Err(DecodeError::InvalidData);
+                    }
+                    let (seg_input, next_input) =
+                        input_rest.split_at(point.compressed - last.compressed);
+                    let (seg_output, next_output) = core::mem::take(&mut output_rest)
+                        .split_at_mut(point.decompressed - last.decompressed);
+                    segments.push(Segment {
+                        input: seg_input,
+                        base: last.decompressed,
+                        output: seg_output,
+                        is_last: false,
+                    });
+                    input_rest = next_input;
+                    output_rest = next_output;
+                    last = *point;
+                }
+                segments.push(Segment {
+                    input: input_rest,
+                    base: last.decompressed,
+                    output: output_rest,
+                    is_last: true,
+                });
+            }
+
+            let threads = thread::available_parallelism()
+                .map(|v| v.get())
+                .unwrap_or(1)
+                .min(segments.len());
+            let chunk_size = segments.len().div_ceil(threads);
+            let deferred = thread::scope(|scope| {
+                let handles = segments
+                    .chunks_mut(chunk_size)
+                    .map(|chunk| {
+                        scope.spawn(move || {
+                            chunk
+                                .iter_mut()
+                                .map(Self::_decode_segment)
+                                .collect::<Result<Vec<_>, _>>()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let mut deferred = Vec::new();
+                for handle in handles {
+                    deferred.extend(handle.join().unwrap_or_else(|e| panic::resume_unwind(e))?);
+                }
+                Ok::<_, DecodeError>(deferred)
+            })?;
+
+            for matches in deferred.iter().flatten() {
+                for cursor in matches.cursor..matches.cursor + matches.len {
+                    output[cursor] = output[cursor - matches.distance];
+                }
+            }
+
+            Ok(())
+        }
+
+        fn _decode_segment(segment: &mut Segment) -> Result<Vec<Deferred>, DecodeError> {
+            let mut deferred = Vec::<Deferred>::new();
+            let base = segment.base;
+            let is_last = segment.is_last;
+            Stk1::_decode_groups(
+                &mut segment.input.iter(),
+                segment.output,
+                |output, cursor, distance, len| {
+                    if distance > base + cursor {
+                        return Err(DecodeError::InvalidData);
+                    }
+                    let len = if is_last {
+                        len.min(output.len() - cursor)
+                    } else if len > output.len() - cursor {
+                        return Err(DecodeError::InvalidData);
+                    } else {
+                        len
+                    };
+
+                    // The source must be within this segment and must not overlap any deferred match.
+                    let is_ready = distance <= cursor && {
+                        let src_start = base + cursor - distance;
+                        let src_end = src_start + len;
+                        let index = deferred.partition_point(|v| v.cursor < src_end);
+                        index == 0 || {
+                            let prev = &deferred[index - 1];
+                            prev.cursor + prev.len <= src_start
+                        }
+                    };
+                    if is_ready {
+                        Stk1::_copy_match(output, cursor, distance, len)
+                    } else {
+                        deferred.push(Deferred {
+                            cursor: base + cursor,
+                            distance,
+                            len,
+                        });
+                        Ok(cursor + len)
+                    }
+                },
+            )?;
+            Ok(deferred)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Configuration, SplitIndex, Stk1};
+
+    #[test]
+    fn parallel_decode() {
+        let mut seed = 0x2545_F491u32;
+        let mut src = Vec::new();
+        while src.len() < 0x40000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            if (seed & 3) == 0 && src.len() > 0x100 {
+                let distance = 1 + (seed >> 8) as usize % src.len().min(0x100);
+                let start = src.len() - distance;
+                for i in 0..(seed >> 24) as usize {
+                    src.push(src[start + i]);
+                }
+            } else {
+                src.push((seed >> 16) as u8 & 0x1F);
+            }
+        }
+
+        for interval in [1, 0x100, 0x1000, 0x10000, usize::MAX] {
+            let (encoded, index) =
+                Stk1::encode_with_index(&src, Configuration::DEFAULT, interval).unwrap();
+            assert_eq!(encoded, Stk1::encode(&src, Configuration::DEFAULT).unwrap());
+
+            let mut serialized = Vec::new();
+            index.write(&mut serialized);
+            let index = SplitIndex::read(&serialized).unwrap();
+
+            let mut decoded = vec![0; src.len()];
+            Stk1::decode_parallel(&encoded, &index, &mut decoded).unwrap();
+            assert_eq!(decoded, src);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Statistics of the compressed data
+
+use crate::{lz::Matches, S7s};
+use core::fmt;
+
+/// Statistics collected by [`Stk1::encode_with_stats`](crate::Stk1::encode_with_stats)
+///
+/// The bytes of the compressed data are counted by their roles:
+/// `literal_bytes + lead_bytes + distance_bytes + s7s_bytes == compressed_len`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeStats {
+    /// Number of bytes of the input
+    pub input_len: usize,
+    /// Number of bytes of the compressed data
+    pub compressed_len: usize,
+    /// Number of groups of literals and matches
+    pub groups: usize,
+    /// Number of bytes stored as literals
+    pub literal_bytes: usize,
+    /// Number of matches by the number of bytes of the distance, from 1 to 4
+    pub matches: [usize; 4],
+    /// Number of elements covered by matches by the number of bytes of the distance
+    pub match_elements: [usize; 4],
+    /// Histograms of match lengths by the number of bytes of the distance
+    ///
+    /// Bucket `i` counts the lengths from `2^i` to `2^(i+1) - 1`.
+    pub match_lens: [[usize; EncodeStats::LEN_BUCKETS]; 4],
+    /// Number of leading bytes of groups and matches
+    pub lead_bytes: usize,
+    /// Number of bytes of distances following the leading bytes of matches
+    pub distance_bytes: usize,
+    /// Number of bytes of S7s for the number of literals, the number of matches and match lengths
+    pub s7s_bytes: usize,
+    /// Number of positions where the match finder searched
+    pub searches: usize,
+    /// Number of candidates examined by the match finder
+    pub probes: usize,
+}
+
+impl EncodeStats {
+    /// Number of buckets of the length histograms
+    pub const LEN_BUCKETS: usize = usize::BITS as usize;
+
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            input_len: 0,
+            compressed_len: 0,
+            groups: 0,
+            literal_bytes: 0,
+            matches: [0; 4],
+            match_elements: [0; 4],
+            match_lens: [[0; Self::LEN_BUCKETS]; 4],
+            lead_bytes: 0,
+            distance_bytes: 0,
+            s7s_bytes: 0,
+            searches: 0,
+            probes: 0,
+        }
+    }
+
+    /// Total number of matches
+    #[inline]
+    pub fn total_matches(&self) -> usize {
+        self.matches.iter().sum()
+    }
+
+    /// Records the leading byte, the number of literals and the literals of a group.
+    #[inline]
+    pub(crate) fn _record_group(&mut self, lit_len: usize, lit_bytes: usize) {
+        self.groups += 1;
+        self.lead_bytes += 1;
+        if lit_len > 15 {
+            self.s7s_bytes += S7s::encoded_len(lit_len);
+        }
+        self.literal_bytes += lit_bytes;
+    }
+
+    /// Records the number of matches of a group if it does not fit in the leading byte.
+    #[inline]
+    pub(crate) fn _record_lz_count(&mut self, lz_count: usize) {
+        if lz_count == 0 || lz_count > 15 {
+            self.s7s_bytes += S7s::encoded_len(lz_count);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn _record_match(&mut self, matches: Matches, distance_len: usize, s7s_len: usize) {
+        let class = distance_len - 1;
+        self.matches[class] += 1;
+        self.match_elements[class] += matches.len;
+        self.match_lens[class][matches.len.ilog2() as usize] += 1;
+        self.lead_bytes += 1;
+        self.distance_bytes += distance_len - 1;
+        self.s7s_bytes += s7s_len;
+    }
+}
+
+impl Default for EncodeStats {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for EncodeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "input {} bytes, output {} bytes, {} groups",
+            self.input_len, self.compressed_len, self.groups
+        )?;
+        writeln!(
+            f,
+            "output: literals {}, leads {}, distances {}, s7s {}",
+            self.literal_bytes, self.lead_bytes, self.distance_bytes, self.s7s_bytes
+        )?;
+        writeln!(
+            f,
+            "match finder: {} searches, {} probes",
+            self.searches, self.probes
+        )?;
+        for (class, lens) in self.match_lens.iter().enumerate() {
+            if self.matches[class] == 0 {
+                continue;
+            }
+            write!(
+                f,
+                "distance {} bytes: {} matches, {} elements, lengths",
+                class + 1,
+                self.matches[class],
+                self.match_elements[class]
+            )?;
+            let last = lens.iter().rposition(|&v| v != 0).unwrap_or(0);
+            for (bucket, count) in lens.iter().enumerate().take(last + 1) {
+                write!(f, " {}+:{}", 1usize << bucket, count)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{Configuration, Stk1};
+
+    #[test]
+    fn stats() {
+        let mut input = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 100].concat();
+        input.extend((0..0x20000u32).map(|v| (v.wrapping_mul(0x9E37_79B1) >> 24) as u8));
+        input.extend_from_slice(b"The quick brown fox");
+        input.extend_from_slice(&[0; 1000]);
+        for config in [
+            Configuration::TINY,
+            Configuration::DEFAULT,
+            Configuration::LARGE,
+        ] {
+            let (encoded, stats) = Stk1::encode_with_stats(&input, config).unwrap();
+            assert_eq!(encoded, Stk1::encode(&input, config).unwrap());
+            assert_eq!(stats.input_len, input.len());
+            assert_eq!(stats.compressed_len, encoded.len());
+            assert_eq!(
+                stats.literal_bytes + stats.lead_bytes + stats.distance_bytes + stats.s7s_bytes,
+                encoded.len()
+            );
+            assert_eq!(
+                stats.literal_bytes + stats.match_elements.iter().sum::<usize>(),
+                input.len()
+            );
+            assert_eq!(
+                stats.match_lens.iter().flatten().sum::<usize>(),
+                stats.total_matches()
+            );
+            assert!(stats.probes >= stats.searches);
+        }
+
+        let (encoded, stats) = Stk1::encode_with_stats(b"a", Configuration::DEFAULT).unwrap();
+        assert_eq!(stats.groups, 1);
+        assert_eq!(
+            stats.lead_bytes + stats.s7s_bytes + stats.literal_bytes,
+            encoded.len()
+        );
+    }
+}
@@ -1,31 +1,75 @@
 // A compatible library for subset of stk1
 
 use crate::{
-    cache::OffsetCache,
-    lz::{self, Matches},
+    cache::{MatchingCache, OffsetCache, MAX_HASH_BITS, MAX_KEY_LEN, MIN_HASH_BITS, MIN_KEY_LEN},
+    lz::Matches,
+    sink::Sink,
+    split::PushSplit,
     var_slice::VarSlice,
-    DecodeError, EncodeError, S7s,
+    DecodeError, Element, EncodeError, EncodeStats, S7s, SplitPoint,
 };
+#[cfg(feature = "alloc")]
+use crate::{SplitIndex, Stk1Encoder};
+#[cfg(feature = "alloc")]
 use alloc::{format, string::String, vec::Vec};
 
 const LZ_MAX_LEN: usize = 0x80_00_00;
 const LZ_MAX_DISTANCE: usize = 0x02_00_00;
+const LZ_LARGE_DISTANCE: usize = 0x01_00_00_00;
 
 const THRESHOLD_LEN1: usize = 16;
 
-const LZ_SHORT_MIN_LEN: usize = 2;
-const LZ_SHORT_MAX_DIST: usize = 8;
+const DEFAULT_CHAIN_DEPTH: usize = 256;
+/// Smallest chain depth that a memory limit reduces to, which is that of level 1
+const MIN_CHAIN_DEPTH: usize = 4;
+const DEFAULT_KEY_LEN: usize = 3;
 
-const LZ_MIN_MID_LEN: usize = 4;
+/// Distances that fit in the leading byte, searched exhaustively for matches shorter than the key
+const LZ_SHORT_MAX_DIST: usize = 8;
 
 /// Stk1 coder
 pub struct Stk1;
 
+/// Implementation of the decoder, selected by [`Stk1::decode_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecoderKind {
+    /// The decoder of [`Stk1::decode`]
+    #[default]
+    Rust,
+    /// The decoder written in x86-64 assembly
+    #[cfg(all(feature = "asm", target_arch = "x86_64", target_os = "linux"))]
+    X86_64,
+}
+
+impl DecoderKind {
+    /// Decoders available on this target
+    pub const AVAILABLE: &'static [Self] = &[
+        Self::Rust,
+        #[cfg(all(feature = "asm", target_arch = "x86_64", target_os = "linux"))]
+        Self::X86_64,
+    ];
+}
+
+/// A group being written by the encoder
+struct Group {
+    /// Offset of the leading byte
+    lead: usize,
+    /// Offset to insert the number of LZs if it does not fit in the leading byte
+    count_at: usize,
+    lz_count: usize,
+}
+
 /// Stk1 configuration
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Configuration {
     max_distance: usize,
     max_len: usize,
+    memory_limit: usize,
+    chain_depth: usize,
+    nice_len: usize,
+    key_len: usize,
+    hash_bits: u32,
+    lazy: bool,
 }
 
 impl Configuration {
@@ -35,16 +79,89 @@ impl Configuration {
     /// Default Dictionary size (128KB, 8MB)
     pub const DEFAULT: Self = Self::new(LZ_MAX_DISTANCE, LZ_MAX_LEN);
 
+    /// Large Dictionary size (16MB, 8MB)
+    pub const LARGE: Self = Self::new(LZ_LARGE_DISTANCE, LZ_MAX_LEN);
+
     pub const MAX: Self = Self::new(LZ_MAX_DISTANCE, 0xFF_FF_FF_FF);
 
     #[inline]
-    const fn new(max_distance: usize, max_len: usize) -> Self {
+    pub(crate) const fn new(max_distance: usize, max_len: usize) -> Self {
         Self {
             max_distance,
             max_len,
+            memory_limit: usize::MAX,
+            chain_depth: DEFAULT_CHAIN_DEPTH,
+            nice_len: THRESHOLD_LEN1,
+            key_len: DEFAULT_KEY_LEN,
+            hash_bits: MAX_HASH_BITS,
+            lazy: true,
         }
     }
 
+    /// Sets the search effort by level from 1 (fastest) to 9 (smallest).
+    ///
+    /// The level determines [`Configuration::chain_depth`], [`Configuration::nice_len`] and [`Configuration::key_len`].
+    /// Level 6 is the default. Levels out of range are clamped.
+    #[inline]
+    pub const fn level(self, level: u32) -> Self {
+        let (depth, nice_len, key_len) = match level {
+            0 | 1 => (4, 8, 5),
+            2 => (8, 16, 5),
+            3 => (16, 16, 4),
+            4 => (32, 16, 4),
+            5 => (64, 16, 4),
+            6 => (DEFAULT_CHAIN_DEPTH, THRESHOLD_LEN1, DEFAULT_KEY_LEN),
+            7 => (512, 64, 3),
+            8 => (1024, 256, 3),
+            _ => (4096, LZ_MAX_LEN, 3),
+        };
+        self.chain_depth(depth).nice_len(nice_len).key_len(key_len)
+    }
+
+    /// Limits the number of candidates examined at each position.
+    ///
+    /// Together with [`Configuration::nice_len`], this bounds the encoding time to O(n·depth) even for adversarial input.
+    #[inline]
+    pub const fn chain_depth(mut self, depth: usize) -> Self {
+        self.chain_depth = if depth > 0 { depth } else { 1 };
+        self
+    }
+
+    /// Stops searching for a longer match once a match of `len` bytes is found.
+    #[inline]
+    pub const fn nice_len(mut self, len: usize) -> Self {
+        self.nice_len = if len > 0 { len } else { 1 };
+        self
+    }
+
+    /// Sets the number of elements hashed to find candidates, between 3 and 5.
+    ///
+    /// Longer keys skip candidates too short to be used, which is faster on large binary input,
+    /// but miss matches shorter than the key.
+    #[inline]
+    pub const fn key_len(mut self, len: usize) -> Self {
+        self.key_len = if len < MIN_KEY_LEN {
+            MIN_KEY_LEN
+        } else if len > MAX_KEY_LEN {
+            MAX_KEY_LEN
+        } else {
+            len
+        };
+        self
+    }
+
+    /// Limits the memory usage of the encoder to `bytes`, counting the output at its upper bound.
+    ///
+    /// The hash table and then the window are reduced until the match finder tables fit in the rest of the limit,
+    /// and the chain depth is reduced with the window.
+    /// [`Stk1Encoder`] also releases tables kept from larger inputs.
+    /// See also [`Stk1::estimate_encoder_memory`].
+    #[inline]
+    pub const fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = bytes;
+        self
+    }
+
     #[inline]
     pub fn max_distance(&self) -> usize {
         self.max_distance
@@ -54,6 +171,59 @@ impl Configuration {
     pub fn max_len(&self) -> usize {
         self.max_len
     }
+
+    #[inline]
+    pub(crate) fn _key_len(&self) -> usize {
+        self.key_len
+    }
+
+    #[inline]
+    pub(crate) fn _is_limited(&self) -> bool {
+        self.memory_limit != usize::MAX
+    }
+
+    /// Returns the number of entries of the match finder tables for `input_len` elements.
+    #[inline]
+    pub(crate) fn _table_sizes(&self, input_len: usize) -> (usize, usize) {
+        OffsetCache::table_sizes(input_len, self.max_distance, self.hash_bits)
+    }
+
+    /// Returns the configuration with the match finder tables reduced until they fit in `entries`.
+    ///
+    /// The hash table is halved while it is larger than a quarter of the links, then the window is halved.
+    /// As the chains of a smaller window hold fewer positions, the chain depth is reduced in proportion.
+    pub(crate) fn _fit_tables(&self, input_len: usize, entries: usize) -> Option<Self> {
+        let mut config = *self;
+        loop {
+            let (head_len, list_len) = config._table_sizes(input_len);
+            if head_len + list_len <= entries {
+                let window = self.max_distance.min(input_len).max(1);
+                let reduced = config.max_distance.min(input_len).max(1);
+                config.chain_depth = (self.chain_depth / (window / reduced))
+                    .max(MIN_CHAIN_DEPTH)
+                    .min(self.chain_depth);
+                return Some(config);
+            }
+            let hash_bits = head_len.trailing_zeros();
+            if head_len > list_len / 4 && hash_bits > MIN_HASH_BITS {
+                config.hash_bits = hash_bits - 1;
+            } else if config.max_distance > 1 {
+                config.max_distance >>= 1;
+            } else {
+                return None;
+            }
+        }
+    }
+
+    /// Returns the configuration to encode `input_len` elements into `output_len` bytes within the memory limit.
+    pub(crate) fn _limited(&self, input_len: usize, output_len: usize) -> Option<Self> {
+        if self._is_limited() {
+            let budget = self.memory_limit.checked_sub(output_len)?;
+            self._fit_tables(input_len, budget / size_of::<usize>())
+        } else {
+            Some(*self)
+        }
+    }
 }
 
 impl Default for Configuration {
@@ -66,6 +236,7 @@ impl Default for Configuration {
 impl Stk1 {
     /// Tests if decoding is successful after encoding.
     /// This will take additional execution time and memory consumption compared to normal encoding.
+    #[cfg(feature = "alloc")]
     pub fn encode_with_test(src: &[u8], config: Configuration) -> Result<Vec<u8>, String> {
         let dst = Self::encode(src, config).map_err(|e| format!("ENCODE ERROR: {:?}", e))?;
 
@@ -75,7 +246,7 @@ impl Stk1 {
         temp.resize(size, 0);
 
         Self::decode(&dst, &mut temp).map_err(|e| format!("DECODE ERROR: {:?}", e))?;
-        if &temp != &src {
+        if temp != src {
             for (index, (p, q)) in src.iter().zip(temp.iter()).enumerate() {
                 if *p != *q {
                     return Err(format!(
@@ -84,147 +255,432 @@ impl Stk1 {
                     ));
                 }
             }
-            return Err(format!("DECODE ERROR: unknown match error"));
+            return Err("DECODE ERROR: unknown match error".into());
         }
 
         Ok(dst)
     }
 
+    #[cfg(feature = "alloc")]
     pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
         let mut output = Vec::new();
+        Stk1Encoder::new(config).encode_into(input, &mut output)?;
+        Ok(output)
+    }
+
+    /// Encodes elements wider than a byte, such as `u16` or `u32`.
+    ///
+    /// Distances and lengths are counted in elements. Use [`Stk1::decode_elements`] to decode.
+    #[cfg(feature = "alloc")]
+    pub fn encode_elements<T: Element>(
+        input: &[T],
+        config: Configuration,
+    ) -> Result<Vec<u8>, EncodeError> {
+        let mut output = Vec::new();
+        Stk1Encoder::new(config).encode_elements_into(input, &mut output)?;
+        Ok(output)
+    }
+
+    /// Encodes and records split points for [`Stk1::decode_parallel`] in a side index.
+    ///
+    /// A split point is recorded at the first group boundary after every `interval` bytes of input.
+    /// The compressed data itself is the same as that of [`Stk1::encode`].
+    #[cfg(feature = "alloc")]
+    pub fn encode_with_index(
+        input: &[u8],
+        config: Configuration,
+        interval: usize,
+    ) -> Result<(Vec<u8>, SplitIndex), EncodeError> {
+        let mut index = SplitIndex::new();
+        let mut output = Vec::new();
+        Stk1Encoder::new(config).encode_with_split(
+            input,
+            &mut output,
+            Some((&mut |point| index.try_push(point), interval.max(1))),
+            None,
+        )?;
+        Ok((output, index))
+    }
+
+    /// Encodes and collects statistics of the compressed data and the match finder.
+    ///
+    /// The compressed data itself is the same as that of [`Stk1::encode`].
+    #[cfg(feature = "alloc")]
+    pub fn encode_with_stats(
+        input: &[u8],
+        config: Configuration,
+    ) -> Result<(Vec<u8>, EncodeStats), EncodeError> {
+        let mut stats = EncodeStats::new();
+        let mut output = Vec::new();
+        Stk1Encoder::new(config).encode_with_split(input, &mut output, None, Some(&mut stats))?;
+        stats.input_len = input.len();
+        stats.compressed_len = output.len();
+        Ok((output, stats))
+    }
+
+    /// Appends the encoded data to `output`, using the match finder provided by the caller.
+    pub(crate) fn _encode<T: Element, S: Sink>(
+        input: &[T],
+        config: &Configuration,
+        mut offset_cache: MatchingCache<T::Key>,
+        output: &mut S,
+        mut split: Option<(PushSplit, usize)>,
+        mut stats: Option<&mut EncodeStats>,
+    ) -> Result<(), EncodeError> {
+        if input.is_empty() {
+            return Ok(());
+        }
+        let mut searches = 0;
+        let mut probes = 0;
+        let base = output.len();
+        let mut next_split = split.as_ref().map(|v| v.1).unwrap_or(usize::MAX);
 
-        let mut offset_cache = OffsetCache::new(input, config.max_distance());
         let mut lit_buf = VarSlice::new(input, 0);
-        let mut lz_buf = Vec::new();
+        let mut group: Option<Group> = None;
 
         let mut cursor = 1;
         offset_cache.advance(cursor);
 
-        while let Some(_) = input.get(cursor) {
-            let count = {
-                let mut matches = Matches::ZERO;
-
-                // Find a long-distance match
-                if let Some(dist_iter) = offset_cache.matches() {
-                    for distance in dist_iter {
-                        let len = lz::matching_len(input, cursor, distance, config.max_len());
-                        if matches.len < len && len >= LZ_MIN_MID_LEN {
-                            matches = Matches { len, distance };
-                            if matches.len >= THRESHOLD_LEN1 {
-                                break;
-                            }
-                        }
-                    }
+        let mut pending = None;
+        while cursor < input.len() {
+            let mut matches = match pending.take() {
+                Some(v) => v,
+                None => {
+                    searches += 1;
+                    Self::_find_match(input, config, &offset_cache, cursor, &mut probes)
                 }
+            };
 
-                // Find a short-distance match
-                if matches.is_zero() {
-                    for distance in 1..=cursor.min(LZ_SHORT_MAX_DIST) {
-                        let len = lz::matching_len(input, cursor, distance, config.max_len());
-                        if len >= LZ_SHORT_MIN_LEN && matches.len < len {
-                            matches = Matches { len, distance };
-                        }
-                    }
+            // Emit a literal instead if the next position has a better match, which also costs a new group
+            let mut advanced = 0;
+            if config.lazy
+                && !matches.is_zero()
+                && matches.len < config.nice_len
+                && cursor + 1 < input.len()
+            {
+                offset_cache.advance(1);
+                advanced = 1;
+                searches += 1;
+                let next = Self::_find_match(input, config, &offset_cache, cursor + 1, &mut probes);
+                let penalty = if group.is_some() { 1 } else { 0 };
+                if Self::_savings::<T>(next) > Self::_savings::<T>(matches) + penalty {
+                    pending = Some(next);
+                    matches = Matches::ZERO;
                 }
+            }
 
+            let count = {
                 if matches.is_zero() {
-                    if lz_buf.len() > 0 {
-                        Self::_flush(&mut output, lit_buf, &mut lz_buf)?;
+                    if let Some(group) = group.take() {
+                        if let Some(stats) = stats.as_deref_mut() {
+                            stats._record_lz_count(group.lz_count);
+                        }
+                        Self::_end_group(output, group)?;
                         lit_buf = VarSlice::new(input, cursor);
+                        if cursor >= next_split {
+                            if let Some((push, interval)) = split.as_mut() {
+                                push(SplitPoint {
+                                    compressed: output.len() - base,
+                                    decompressed: cursor,
+                                })?;
+                                next_split = cursor + *interval;
+                            }
+                        }
                     } else {
                         lit_buf.expand(1);
                     }
                     1
                 } else {
-                    lz_buf.push(matches);
+                    let group = match group.as_mut() {
+                        Some(v) => v,
+                        None => {
+                            if let Some(stats) = stats.as_deref_mut() {
+                                stats._record_group(lit_buf.len(), lit_buf.len() * T::SIZE);
+                            }
+                            group.insert(Self::_begin_group(output, lit_buf)?)
+                        }
+                    };
+                    group.lz_count += 1;
+                    if let Some(stats) = stats.as_deref_mut() {
+                        stats._record_match(
+                            matches,
+                            Self::_distance_len(matches.distance),
+                            Self::_len_s7s_len(matches.len),
+                        );
+                    }
+                    Self::_write_match(output, matches)?;
                     matches.len
                 }
             };
-            offset_cache.advance(count);
+            offset_cache.advance(count - advanced);
             cursor += count;
         }
-        Self::_flush(&mut output, lit_buf, &mut lz_buf)?;
+        if let Some(stats) = stats {
+            match &group {
+                Some(group) => stats._record_lz_count(group.lz_count),
+                None => {
+                    stats._record_group(lit_buf.len(), lit_buf.len() * T::SIZE);
+                    stats._record_lz_count(0);
+                }
+            }
+            stats.searches += searches;
+            stats.probes += probes;
+        }
+        match group {
+            Some(group) => Self::_end_group(output, group),
+            None => Self::_write_last_group(output, lit_buf),
+        }
+    }
 
-        Ok(output)
+    /// Returns the match that saves the most bytes at `cursor`, or [`Matches::ZERO`] if none saves any.
+    fn _find_match<T: Element>(
+        input: &[T],
+        config: &Configuration,
+        offset_cache: &MatchingCache<T::Key>,
+        cursor: usize,
+        probes: &mut usize,
+    ) -> Matches {
+        let mut best = Matches::ZERO;
+        let mut best_savings = 0;
+        let mut consider = |best: &mut Matches, distance| {
+            let len = T::matching_len(input, cursor, distance, config.max_len());
+            if len > best.len || distance < best.distance {
+                let matches = Matches { len, distance };
+                let savings = Self::_savings::<T>(matches);
+                if savings > best_savings
+                    || (savings > 0 && savings == best_savings && len > best.len)
+                {
+                    *best = matches;
+                    best_savings = savings;
+                }
+            }
+        };
+
+        // Find a match in the hash chain.
+        // Candidates are nearest first, so only a longer match can save more bytes.
+        if let Some(dist_iter) = offset_cache.matches() {
+            for distance in dist_iter.take(config.chain_depth) {
+                *probes += 1;
+                let end = cursor + best.len;
+                if end < input.len() && input[end] != input[end - distance] {
+                    continue;
+                }
+                consider(&mut best, distance);
+                if best.len >= config.nice_len {
+                    return best;
+                }
+            }
+        }
+
+        // Find a match shorter than the key at distances of the cheapest token
+        for distance in 1..=cursor.min(LZ_SHORT_MAX_DIST) {
+            *probes += 1;
+            consider(&mut best, distance);
+        }
+        best
     }
 
-    fn _flush(
-        output: &mut Vec<u8>,
-        lit_buf: VarSlice<u8>,
-        lz_buf: &mut Vec<Matches>,
-    ) -> Result<(), EncodeError> {
+    /// Returns the number of bytes saved by the match compared to literals, or 0 if it saves nothing.
+    #[inline]
+    fn _savings<T: Element>(matches: Matches) -> usize {
+        if matches.is_zero() {
+            return 0;
+        }
+        let cost = Self::_distance_len(matches.distance) + Self::_len_s7s_len(matches.len);
+        (matches.len * T::SIZE).saturating_sub(cost)
+    }
+
+    /// Returns the number of bytes of S7s for the match length, which is 0 if it fits in the leading byte.
+    #[inline]
+    fn _len_s7s_len(len: usize) -> usize {
+        let lz_len = len - 1;
+        if lz_len == 0 || lz_len > 15 {
+            S7s::encoded_len(lz_len)
+        } else {
+            0
+        }
+    }
+
+    /// Writes the leading byte and literals of a group whose number of LZs is not yet known.
+    fn _begin_group<T: Element, S: Sink>(
+        output: &mut S,
+        lit_buf: VarSlice<T>,
+    ) -> Result<Group, EncodeError> {
         // Literals of length 0 are impossible.
         assert!(lit_buf.len() > 0);
 
         let lit_len = lit_buf.len();
-        let lz_count = lz_buf.len();
-        let leading = ((if lit_len > 15 { 0 } else { lit_len })
-            | ((if lz_count > 15 { 0 } else { lz_count }) << 4)) as u8;
-        output.push(leading);
+        let lead = output.len();
+        output.push((if lit_len > 15 { 0 } else { lit_len }) as u8)?;
         if lit_len > 15 {
-            S7s::write(output, lit_len);
-        }
-        if lz_count > 15 || lz_count == 0 {
-            // Usually the number of LZs is not zero, but may be generated as a set with the last literal in the file
-            S7s::write(output, lz_count);
+            output.write_s7s(lit_len)?;
         }
+        let count_at = output.len();
 
-        output.extend_from_slice(lit_buf.into_slice());
-
-        for matches in lz_buf.iter() {
-            let lz_len = matches.len - 1;
-            let distance = matches.distance - 1;
-            let (dist_lead, dist_len, dist_trail) = if distance < 8 {
-                ((distance << 1) as u8 | 0x01, 0, 0)
-            } else if distance < 0x4_00 {
-                (((distance >> 6) & 0x0E) as u8, 1, distance & 0x7F)
-            } else if distance < 0x2_00_00 {
-                (((distance >> 13) & 0x0E) as u8, 2, distance & 0x3F_FF)
-            } else {
-                unreachable!()
-            };
-            let leading = dist_lead | (if lz_len > 15 { 0 } else { (lz_len << 4) as u8 });
-            output.push(leading);
-            match dist_len {
-                1 => {
-                    output.push(((dist_trail << 1) as u8) | 1);
-                }
-                2 => {
-                    output.push(((dist_trail >> 6) as u8) & 0xFE);
-                    output.push(((dist_trail << 1) as u8) | 1);
-                }
-                _ => {}
-            }
-            if lz_len > 15 {
-                S7s::write(output, lz_len);
-            }
+        T::write_literals(output, lit_buf.into_slice())?;
+
+        Ok(Group {
+            lead,
+            count_at,
+            lz_count: 0,
+        })
+    }
+
+    /// Completes the group with the number of LZs written.
+    fn _end_group<S: Sink>(output: &mut S, group: Group) -> Result<(), EncodeError> {
+        let lz_count = group.lz_count;
+        if lz_count > 15 {
+            let mut buf = [0; S7s::MAX_LEN];
+            let len = S7s::write_to_slice(&mut buf, lz_count).unwrap();
+            output.insert(group.count_at, &buf[..len])
+        } else {
+            *output.get_mut(group.lead) |= (lz_count << 4) as u8;
+            Ok(())
         }
+    }
 
-        lz_buf.clear();
+    /// Writes the last group, which has no LZ.
+    fn _write_last_group<T: Element, S: Sink>(
+        output: &mut S,
+        lit_buf: VarSlice<T>,
+    ) -> Result<(), EncodeError> {
+        let group = Self::_begin_group(output, lit_buf)?;
+        // Usually the number of LZs is not zero, but may be generated as a set with the last literal in the file
+        output.insert(group.count_at, &[S7s::ZERO])
+    }
+
+    fn _write_match<S: Sink>(output: &mut S, matches: Matches) -> Result<(), EncodeError> {
+        let lz_len = matches.len - 1;
+        let distance = matches.distance - 1;
+        // The upper 3 bits of the distance are in the leading byte, followed by 7 bits per byte.
+        let dist_len = Self::_distance_len(matches.distance) - 1;
+        let dist_lead = if dist_len == 0 {
+            (distance << 1) as u8 | 0x01
+        } else {
+            ((distance >> (7 * dist_len - 1)) & 0x0E) as u8
+        };
+        // A length of 1 element also needs S7s, as 0 in the leading byte means S7s follows.
+        let lz_in_lead = lz_len > 0 && lz_len <= 15;
+        let leading = dist_lead | (if lz_in_lead { (lz_len << 4) as u8 } else { 0 });
+        output.push(leading)?;
+        for shift in (1..dist_len).rev() {
+            output.push(((distance >> (7 * shift - 1)) as u8) & 0xFE)?;
+        }
+        if dist_len > 0 {
+            output.push(((distance << 1) as u8) | 1)?;
+        }
+        if !lz_in_lead {
+            output.write_s7s(lz_len)?;
+        }
 
         Ok(())
     }
 
+    /// Returns the number of bytes needed to encode the distance, including the leading byte.
+    #[inline]
+    fn _distance_len(distance: usize) -> usize {
+        let mut distance = (distance - 1) >> 3;
+        let mut len = 1;
+        while distance > 0 {
+            distance >>= 7;
+            len += 1;
+        }
+        len
+    }
+
+    /// Returns the upper bound of the size of the data compressed from `input_len` bytes.
+    ///
+    /// Every LZ is shorter than the bytes it covers since it must save bytes, which pays for the leading byte and
+    /// the number of LZs of its group. What remains is the length of literals longer than 15 bytes,
+    /// whose S7s takes at most 1 byte per 16 bytes of literals,
+    /// and the leading byte and zero number of LZs of the last group.
+    #[inline]
+    pub const fn max_compressed_len(input_len: usize) -> usize {
+        if input_len == 0 {
+            0
+        } else {
+            input_len.saturating_add(input_len / 16).saturating_add(2)
+        }
+    }
+
     pub fn decode(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
-        let mut iter = input.iter();
-        let iter = &mut iter;
+        Self::_decode(&mut input.iter(), output)
+    }
+
+    /// Decodes `input` into `output` with the decoder of `kind`.
+    ///
+    /// All decoders return the same result and output.
+    #[inline]
+    pub fn decode_with(
+        kind: DecoderKind,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), DecodeError> {
+        match kind {
+            DecoderKind::Rust => Self::decode(input, output),
+            #[cfg(all(feature = "asm", target_arch = "x86_64", target_os = "linux"))]
+            DecoderKind::X86_64 => crate::asm::_decode(input, output),
+        }
+    }
+
+    /// Decodes the data read from `iter`, which stops reading after the last token.
+    pub(crate) fn _decode<'a, I>(iter: &mut I, output: &mut [u8]) -> Result<(), DecodeError>
+    where
+        I: Iterator<Item = &'a u8>,
+    {
+        Self::_decode_groups(iter, output, Self::_copy_match)
+    }
+
+    /// Copies `len` elements from `distance` elements before `cursor`, limited by the end of `output`,
+    /// and returns the cursor after the match.
+    #[inline]
+    pub(crate) fn _copy_match<T: Element>(
+        output: &mut [T],
+        cursor: usize,
+        distance: usize,
+        len: usize,
+    ) -> Result<usize, DecodeError> {
+        if distance > cursor {
+            return Err(DecodeError::InvalidData);
+        }
+        let end = cursor + len.min(output.len() - cursor);
+        for cursor in cursor..end {
+            output[cursor] = output[cursor - distance];
+        }
+        Ok(end)
+    }
+
+    /// Decodes the groups read from `iter` into `output`.
+    ///
+    /// Each match is passed to `copy_match` with the cursor, distance and length,
+    /// which returns the cursor after the match.
+    #[inline]
+    pub(crate) fn _decode_groups<'a, T, I, F>(
+        iter: &mut I,
+        output: &mut [T],
+        mut copy_match: F,
+    ) -> Result<(), DecodeError>
+    where
+        T: Element,
+        I: Iterator<Item = &'a u8>,
+        F: FnMut(&mut [T], usize, usize, usize) -> Result<usize, DecodeError>,
+    {
         let mut cursor = 0;
         while cursor < output.len() {
             let lead_lz = iter.next().ok_or(DecodeError::InvalidData)?;
-            let by = lead_lz & 0x0F;
-            let lz = lead_lz >> 4;
-            let by = if by == 0 {
-                S7s::read(iter).ok_or(DecodeError::InvalidData)?
-            } else {
-                by as usize
+            let by = match lead_lz & 0x0F {
+                0 => S7s::read(iter).ok_or(DecodeError::InvalidData)?,
+                by => by as usize,
             };
-            let lz = if lz == 0 {
-                S7s::read(iter).ok_or(DecodeError::InvalidData)?
-            } else {
-                lz as usize
+            let lz = match lead_lz >> 4 {
+                0 => S7s::read(iter).ok_or(DecodeError::InvalidData)?,
+                lz => lz as usize,
             };
-            for p in iter.take(by) {
-                output[cursor] = *p;
+            for _ in 0..by {
+                let Some(literal) = T::read_literal(iter) else {
+                    break;
+                };
+                *output.get_mut(cursor).ok_or(DecodeError::InvalidData)? = literal;
                 cursor += 1;
             }
             if cursor >= output.len() {
@@ -232,29 +688,123 @@ impl Stk1 {
             }
             for _ in 0..lz {
                 let lead_cp = *iter.next().ok_or(DecodeError::InvalidData)?;
-                let ds = S7s::read_with_acc(iter, lead_cp as usize & 0x0F)
-                    .ok_or(DecodeError::InvalidData)?;
-                let ds = ds + 1;
-                let cp = lead_cp >> 4;
-                let cp = if cp == 0 {
-                    S7s::read(iter).ok_or(DecodeError::InvalidData)?
-                } else {
-                    cp as usize
+                let distance = S7s::read_with_acc(iter, lead_cp as usize & 0x0F)
+                    .ok_or(DecodeError::InvalidData)?
+                    + 1;
+                let len = match lead_cp >> 4 {
+                    0 => S7s::read(iter).ok_or(DecodeError::InvalidData)?,
+                    len => len as usize,
+                } + 1;
+                cursor = copy_match(output, cursor, distance, len)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes `input` into an array of the decompressed size in const context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data is invalid, which is a compile error in const context.
+    ///
+    /// ```
+    /// use libstk1::Stk1;
+    ///
+    /// static TABLE: [u8; 4] = Stk1::decode_const(&[0x11, 0x41, 0x21]);
+    /// assert_eq!(&TABLE, b"AAAA");
+    /// ```
+    #[inline]
+    pub const fn decode_const<const N: usize>(input: &[u8]) -> [u8; N] {
+        match Self::try_decode_const(input) {
+            Ok(output) => output,
+            Err(_) => panic!("invalid stk1 data"),
+        }
+    }
+
+    /// Decodes `input` into an array of the decompressed size in const context.
+    ///
+    /// This returns the same result as [`Stk1::decode`].
+    pub const fn try_decode_const<const N: usize>(input: &[u8]) -> Result<[u8; N], DecodeError> {
+        let mut output = [0; N];
+        let mut index = 0;
+        let mut cursor = 0;
+        while cursor < N {
+            if index >= input.len() {
+                return Err(DecodeError::InvalidData);
+            }
+            let lead_lz = input[index];
+            index += 1;
+            let mut by = (lead_lz & 0x0F) as usize;
+            if by == 0 {
+                let Some((value, next)) = S7s::_read_const(input, index, 0) else {
+                    return Err(DecodeError::InvalidData);
                 };
+                by = value;
+                index = next;
+            }
+            let mut lz = (lead_lz >> 4) as usize;
+            if lz == 0 {
+                let Some((value, next)) = S7s::_read_const(input, index, 0) else {
+                    return Err(DecodeError::InvalidData);
+                };
+                lz = value;
+                index = next;
+            }
+            while by > 0 && index < input.len() {
+                if cursor >= N {
+                    return Err(DecodeError::InvalidData);
+                }
+                output[cursor] = input[index];
+                cursor += 1;
+                index += 1;
+                by -= 1;
+            }
+            if cursor >= N {
+                break;
+            }
+            while lz > 0 {
+                if index >= input.len() {
+                    return Err(DecodeError::InvalidData);
+                }
+                let lead_cp = input[index];
+                let Some((ds, next)) =
+                    S7s::_read_const(input, index + 1, (lead_cp & 0x0F) as usize)
+                else {
+                    return Err(DecodeError::InvalidData);
+                };
+                index = next;
+                let ds = ds + 1;
+                let mut cp = (lead_cp >> 4) as usize;
+                if cp == 0 {
+                    let Some((value, next)) = S7s::_read_const(input, index, 0) else {
+                        return Err(DecodeError::InvalidData);
+                    };
+                    cp = value;
+                    index = next;
+                }
                 let cp = cp + 1;
                 if ds > cursor {
                     return Err(DecodeError::InvalidData);
                 }
-                let cp = cp.min(output.len() - cursor);
-                for _ in 0..cp {
+                let end = if cp < N - cursor { cursor + cp } else { N };
+                while cursor < end {
                     output[cursor] = output[cursor - ds];
                     cursor += 1;
                 }
+                lz -= 1;
             }
         }
-        Ok(())
+        Ok(output)
+    }
+
+    /// Decodes data encoded by [`Stk1::encode_elements`] into `output`.
+    ///
+    /// For `u8`, this is the same as [`Stk1::decode`].
+    pub fn decode_elements<T: Element>(input: &[u8], output: &mut [T]) -> Result<(), DecodeError> {
+        Self::_decode_groups(&mut input.iter(), output, Self::_copy_match)
     }
 
+    #[cfg(feature = "alloc")]
     pub fn decode_to_vec(input: &[u8], size: usize) -> Result<Vec<u8>, DecodeError> {
         let mut vec = Vec::new();
         vec.try_reserve_exact(size)
@@ -263,3 +813,186 @@ impl Stk1 {
         Self::decode(input, &mut vec).map(|_| vec)
     }
 }
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::LZ_SHORT_MAX_DIST;
+    use crate::{Configuration, Stk1, TokenKind};
+
+    /// Returns the position, distance and length of each match.
+    fn matches(input: &[u8], config: Configuration) -> Vec<(usize, usize, usize)> {
+        let encoded = Stk1::encode(input, config).unwrap();
+        assert_eq!(Stk1::decode_to_vec(&encoded, input.len()).unwrap(), input);
+        Stk1::tokens(&encoded, input.len())
+            .filter_map(|token| {
+                let token = token.unwrap();
+                match token.kind {
+                    TokenKind::Match { distance, len } => Some((token.position, distance, len)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns bytes that do not match each other or ASCII.
+    fn filler(len: usize) -> Vec<u8> {
+        (0..len).map(|v| 0x80 | (v % 0x80) as u8).collect()
+    }
+
+    /// Data decoded at compile time
+    const AAAA: [u8; 4] = Stk1::decode_const(&[0x11, 0x41, 0x21]);
+
+    #[test]
+    fn decode_const() {
+        assert_eq!(&AAAA, b"AAAA");
+
+        let text = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 20].concat();
+        let encoded = Stk1::encode(&text, Configuration::DEFAULT).unwrap();
+        let output: [u8; 900] = Stk1::decode_const(&encoded);
+        assert_eq!(output.as_slice(), text);
+
+        // Same results as `decode` for truncated and corrupted data and wrong sizes
+        for len in 0..encoded.len() {
+            let mut corrupted = encoded.clone();
+            corrupted[len] ^= 0x08;
+            for input in [&encoded[..len], &corrupted] {
+                let mut expected = [0; 900];
+                match Stk1::decode(input, &mut expected) {
+                    Ok(()) => assert_eq!(Stk1::try_decode_const(input).unwrap(), expected),
+                    Err(_) => assert!(Stk1::try_decode_const::<900>(input).is_err()),
+                }
+            }
+        }
+        assert!(Stk1::try_decode_const::<901>(&encoded).is_err());
+        let mut expected = [0; 450];
+        match Stk1::decode(&encoded, &mut expected) {
+            Ok(()) => assert_eq!(Stk1::try_decode_const(&encoded).unwrap(), expected),
+            Err(_) => assert!(Stk1::try_decode_const::<450>(&encoded).is_err()),
+        }
+    }
+
+    #[test]
+    fn large_window() {
+        let mut seed = 0x2545_F491u32;
+        let mut random = |len: usize| {
+            (0..len)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 17;
+                    seed ^= seed << 5;
+                    seed as u8
+                })
+                .collect::<Vec<_>>()
+        };
+        // Repeats with 4-byte distances, beyond the window of `DEFAULT`
+        for gap in [0x20001, 0x30000, 0x200000] {
+            let block = random(0x1000);
+            let mut input = block.clone();
+            input.extend(random(gap - block.len()));
+            input.extend_from_slice(&block);
+
+            let encoded = Stk1::encode(&input, Configuration::LARGE).unwrap();
+            assert_eq!(Stk1::decode_to_vec(&encoded, input.len()).unwrap(), input);
+            assert!(
+                Stk1::tokens(&encoded, input.len()).any(|token| token.unwrap().kind
+                    == TokenKind::Match {
+                        distance: gap,
+                        len: block.len()
+                    })
+            );
+        }
+    }
+
+    #[test]
+    fn memory_limit_tables() {
+        // The hash table is reduced before the window, and the chain depth with the window
+        let config = Configuration::LARGE;
+        let (head_len, list_len) = config._table_sizes(0x10_0000);
+        let fitted = config
+            ._fit_tables(0x10_0000, list_len + head_len / 2)
+            .unwrap();
+        assert_eq!(fitted.max_distance(), config.max_distance());
+        assert_eq!(fitted._table_sizes(0x10_0000), (head_len / 2, list_len));
+        assert_eq!(fitted.chain_depth, config.chain_depth);
+
+        let fitted = config._fit_tables(0x10_0000, list_len / 4).unwrap();
+        assert!(fitted.max_distance() <= 0x10_0000 / 8);
+        assert!(fitted.chain_depth < config.chain_depth);
+        let (head_len, list_len) = fitted._table_sizes(0x10_0000);
+        assert!(head_len + list_len <= 0x10_0000 / 4);
+    }
+
+    #[test]
+    fn chain_depth() {
+        // Many positions of both inputs share one hash chain
+        let run = [b"ab".as_slice(); 0x4000].concat();
+        let mut seed = 0x2545_F491u32;
+        let mut keys = Vec::new();
+        for _ in 0..0x2000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            keys.extend_from_slice(b"abc");
+            keys.push(seed as u8);
+        }
+        for input in [&run, &keys] {
+            for depth in [1, 4, 64] {
+                let config = Configuration::DEFAULT.chain_depth(depth);
+                let (encoded, stats) = Stk1::encode_with_stats(input, config).unwrap();
+                assert!(
+                    stats.probes <= stats.searches * (depth + LZ_SHORT_MAX_DIST),
+                    "{} {} {}",
+                    depth,
+                    stats.probes,
+                    stats.searches
+                );
+                assert_eq!(&Stk1::decode_to_vec(&encoded, input.len()).unwrap(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn savings() {
+        // 6 bytes at a 3-byte distance save 3 bytes, and 5 bytes at a 1-byte distance save 4 bytes
+        let mut input = b"vwxyzQ#".to_vec();
+        input.extend(filler(0x500));
+        let near = input.len();
+        input.extend_from_slice(b"vwxyz123");
+        let cursor = input.len();
+        input.extend_from_slice(b"vwxyzQ!");
+        let matches = matches(&input, Configuration::DEFAULT);
+        assert!(
+            matches.contains(&(cursor, cursor - near, 5)),
+            "{:?}",
+            matches
+        );
+        assert!(!matches.iter().any(|v| v.0 == cursor && v.2 == 6));
+    }
+
+    #[test]
+    fn lazy() {
+        // A 3-byte match at `cursor` is followed by a 13-byte match at the next position
+        let mut input = b"Xabc#bcdefghijklmn%".to_vec();
+        let source = 5;
+        input.extend(filler(0x20));
+        let cursor = input.len();
+        input.extend_from_slice(b"abcdefghijklmn!");
+
+        let greedy = matches(
+            &input,
+            Configuration {
+                lazy: false,
+                ..Configuration::DEFAULT
+            },
+        );
+        assert!(greedy.contains(&(cursor, cursor - 1, 3)), "{:?}", greedy);
+
+        let lazy = matches(&input, Configuration::DEFAULT);
+        assert!(
+            lazy.contains(&(cursor + 1, cursor + 1 - source, 13)),
+            "{:?}",
+            lazy
+        );
+        assert!(!lazy.iter().any(|v| v.0 == cursor));
+    }
+}
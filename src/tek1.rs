@@ -0,0 +1,228 @@
+//! tek1 file header
+
+#[cfg(feature = "alloc")]
+use crate::{Configuration, Stk1Encoder};
+use crate::{DecodeError, EncodeError, S7s, Stk1};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// The tek1 file format of OSASK, which is stk1 data with a header
+///
+/// The header consists of the signature, the decompressed size in S7s, the flags in S7s and a zero in S7s.
+/// This is the header read by `tek1_checkformat` and `tek1_decode` in `extra/x86/stk1.asm`.
+///
+/// The flags written by this library only have the block size, which must not be smaller than the decompressed size.
+/// Headers with the extra field in bit 6 can be read, but its value is ignored.
+pub struct Tek1;
+
+/// Fields of the tek1 header, read by [`Tek1::parse_header`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tek1Header {
+    /// Decompressed size
+    pub size: usize,
+    /// Block size, which is `2^(bsiz + 8)` bytes
+    pub bsiz: usize,
+    /// `MD` field of the flags
+    pub md: usize,
+    /// `MDS` field of the flags
+    pub mds: usize,
+    /// Extra field, present if bit 6 of the flags is set
+    pub extra: Option<usize>,
+    /// Length of the header
+    pub len: usize,
+}
+
+impl Tek1 {
+    /// Signature at the beginning of the file
+    pub const SIGNATURE: [u8; 16] = [
+        0x83, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, b'O', b'S', b'A', b'S', b'K', b'C', b'M',
+        b'P',
+    ];
+
+    /// Maximum decompressed size that the block size can represent (8MB)
+    pub const MAX_SIZE: usize = 1 << (Self::MAX_BLOCK_BITS + 8);
+
+    /// Maximum length of the header
+    pub const MAX_HEADER_LEN: usize = Self::SIGNATURE.len() + S7s::MAX_LEN + 3;
+
+    const MAX_BLOCK_BITS: usize = 15;
+
+    const FLAG_MUST1: usize = 0x01;
+    const FLAG_MUST0: usize = 0x20;
+    const FLAG_EXTRA: usize = 0x40;
+    const FLAGS_LIMIT: usize = 0x8000;
+
+    /// Returns the flags for the decompressed size, or `None` if the size is too large.
+    #[inline]
+    fn _flags(size: usize) -> Option<usize> {
+        let mut bsiz = 0;
+        while (1 << (bsiz + 8)) < size {
+            bsiz += 1;
+            if bsiz > Self::MAX_BLOCK_BITS {
+                return None;
+            }
+        }
+        Some(Self::FLAG_MUST1 | (bsiz << 1))
+    }
+
+    /// Writes the header for `size` bytes of decompressed data and returns the number of bytes written.
+    ///
+    /// Fails with [`EncodeError::InputTooLarge`] if `size` exceeds [`Tek1::MAX_SIZE`].
+    pub fn write_header(output: &mut [u8], size: usize) -> Result<usize, EncodeError> {
+        let flags = Self::_flags(size).ok_or(EncodeError::InputTooLarge)?;
+        let mut buf = [0; Self::MAX_HEADER_LEN];
+        let mut len = Self::SIGNATURE.len();
+        buf[..len].copy_from_slice(&Self::SIGNATURE);
+        for value in [size, flags, 0] {
+            len += S7s::write_to_slice(&mut buf[len..], value).unwrap();
+        }
+        output
+            .get_mut(..len)
+            .ok_or(EncodeError::OutputTooSmall)?
+            .copy_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    /// Reads the header and returns the decompressed size and the length of the header.
+    #[inline]
+    pub fn read_header(input: &[u8]) -> Result<(usize, usize), DecodeError> {
+        Self::parse_header(input).map(|header| (header.size, header.len))
+    }
+
+    /// Reads all fields of the header.
+    pub fn parse_header(input: &[u8]) -> Result<Tek1Header, DecodeError> {
+        let mut iter = input.iter();
+        let mut header = Self::_parse_header(&mut iter)?;
+        header.len = input.len() - iter.as_slice().len();
+        Ok(header)
+    }
+
+    /// Reads the header from `iter` except its length, which is left zero.
+    pub(crate) fn _parse_header<'a, I>(iter: &mut I) -> Result<Tek1Header, DecodeError>
+    where
+        I: Iterator<Item = &'a u8>,
+    {
+        for expected in Self::SIGNATURE {
+            if iter.next() != Some(&expected) {
+                return Err(DecodeError::InvalidData);
+            }
+        }
+        let size = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+        let flags = S7s::read(iter).ok_or(DecodeError::InvalidData)?;
+        let bsiz = (flags >> 1) & 0x0F;
+        if flags >= Self::FLAGS_LIMIT
+            || (flags & Self::FLAG_MUST1) == 0
+            || (flags & Self::FLAG_MUST0) != 0
+            || (1 << (bsiz + 8)) < size
+        {
+            return Err(DecodeError::InvalidData);
+        }
+        let extra = if (flags & Self::FLAG_EXTRA) != 0 {
+            Some(S7s::read(iter).ok_or(DecodeError::InvalidData)?)
+        } else {
+            None
+        };
+        if S7s::read(iter).ok_or(DecodeError::InvalidData)? != 0 {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(Tek1Header {
+            size,
+            bsiz,
+            md: (flags >> 7) & 0x0F,
+            mds: (flags >> 11) & 0x0F,
+            extra,
+            len: 0,
+        })
+    }
+
+    /// Returns the decompressed size if `input` begins with a valid header.
+    #[inline]
+    pub fn check_format(input: &[u8]) -> Option<usize> {
+        Self::read_header(input).ok().map(|(size, _)| size)
+    }
+
+    /// Encodes `input` with the header.
+    #[cfg(feature = "alloc")]
+    pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
+        let mut header = [0; Self::MAX_HEADER_LEN];
+        let len = Self::write_header(&mut header, input.len())?;
+        let mut output = Vec::new();
+        output
+            .try_reserve(len)
+            .map_err(|_| EncodeError::OutOfMemory)?;
+        output.extend_from_slice(&header[..len]);
+        Stk1Encoder::new(config).encode_into(input, &mut output)?;
+        Ok(output)
+    }
+
+    /// Decodes data with the header into `output`, whose length must be the decompressed size.
+    pub fn decode(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+        let (size, header_len) = Self::read_header(input)?;
+        if size != output.len() {
+            return Err(DecodeError::InvalidData);
+        }
+        Stk1::decode(&input[header_len..], output)
+    }
+
+    /// Decodes data with the header into a new vector of the decompressed size.
+    #[cfg(feature = "alloc")]
+    pub fn decode_to_vec(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let (size, header_len) = Self::read_header(input)?;
+        Stk1::decode_to_vec(&input[header_len..], size)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{Configuration, DecodeError, EncodeError, Tek1};
+
+    #[test]
+    fn header() {
+        for size in [0, 1, 0x100, 0x101, 0x12345, Tek1::MAX_SIZE] {
+            let mut buf = [0; Tek1::MAX_HEADER_LEN];
+            let len = Tek1::write_header(&mut buf, size).unwrap();
+            assert_eq!(Tek1::read_header(&buf).unwrap(), (size, len));
+            assert_eq!(Tek1::check_format(&buf[..len]), Some(size));
+            assert!(Tek1::read_header(&buf[..len - 1]).is_err());
+        }
+        assert!(matches!(
+            Tek1::write_header(&mut [0; Tek1::MAX_HEADER_LEN], Tek1::MAX_SIZE + 1),
+            Err(EncodeError::InputTooLarge)
+        ));
+        assert!(matches!(
+            Tek1::write_header(&mut [0; 18], 0),
+            Err(EncodeError::OutputTooSmall)
+        ));
+
+        // Block size smaller than the decompressed size
+        let mut buf = [0; Tek1::MAX_HEADER_LEN];
+        let len = Tek1::write_header(&mut buf, 0x101).unwrap();
+        buf[len - 2] = 0x03;
+        assert!(matches!(
+            Tek1::read_header(&buf),
+            Err(DecodeError::InvalidData)
+        ));
+        assert!(Tek1::check_format(b"OSASKCMP").is_none());
+
+        let mut buf = Tek1::SIGNATURE.to_vec();
+        // Size 0x10, flags 0xC5 (bsiz 2, extra field, MD 1), extra 5
+        buf.extend_from_slice(&[0x21, 0x02, 0x8B, 0x0B, 0x01]);
+        let header = Tek1::parse_header(&buf).unwrap();
+        assert_eq!((header.size, header.bsiz), (0x10, 2));
+        assert_eq!((header.md, header.mds, header.extra), (1, 0, Some(5)));
+        assert_eq!(header.len, buf.len());
+    }
+
+    #[test]
+    fn roundtrip() {
+        let input = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 100].concat();
+        let encoded = Tek1::encode(&input, Configuration::DEFAULT).unwrap();
+        assert!(encoded.starts_with(&Tek1::SIGNATURE));
+        assert_eq!(Tek1::decode_to_vec(&encoded).unwrap(), input);
+
+        let mut output = vec![0; input.len()];
+        Tek1::decode(&encoded, &mut output).unwrap();
+        assert_eq!(output, input);
+        assert!(Tek1::decode(&encoded, &mut output[1..]).is_err());
+    }
+}
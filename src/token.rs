@@ -0,0 +1,218 @@
+//! Tokens of the compressed data
+
+use crate::{DecodeError, S7s, Stk1};
+
+/// Kind of a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind<'a> {
+    /// Leading byte of a group with the number of literals and matches that follow
+    Group { literals: usize, matches: usize },
+    /// Literals of a group
+    Literals(&'a [u8]),
+    /// Copy of `len` bytes from `distance` bytes before
+    Match { distance: usize, len: usize },
+}
+
+/// A token of the compressed data, generated by [`Stk1::tokens`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    /// Offset of the token in the compressed data
+    pub offset: usize,
+    /// Number of bytes of the token in the compressed data
+    pub encoded_len: usize,
+    /// Offset in the decompressed data where the output of the token begins
+    pub position: usize,
+    pub kind: TokenKind<'a>,
+    decoded_len: usize,
+}
+
+impl Token<'_> {
+    /// Returns the number of bytes that the token outputs.
+    ///
+    /// This is less than the length of the last match if the decoder cuts it off at the decompressed size.
+    #[inline]
+    pub fn decoded_len(&self) -> usize {
+        self.decoded_len
+    }
+}
+
+/// Iterator over the tokens of the compressed data
+///
+/// The iteration ends at the end of the input or when the decompressed size is reached, as the decoder does.
+/// After an error, the iterator returns `None`.
+pub struct Tokens<'a> {
+    input: &'a [u8],
+    offset: usize,
+    position: usize,
+    size: usize,
+    literals: usize,
+    matches: usize,
+    is_done: bool,
+}
+
+impl<'a> Tokens<'a> {
+    /// Returns the offset in the compressed data where the next token begins, or where an error occurred.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of bytes decompressed so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn _next(&mut self) -> Result<Option<Token<'a>>, DecodeError> {
+        let rest = &self.input[self.offset..];
+        let mut iter = rest.iter();
+        let iter = &mut iter;
+        let (kind, decoded_len) = if self.literals > 0 {
+            let len = self.literals;
+            self.literals = 0;
+            if self.size - self.position < len {
+                return Err(DecodeError::InvalidData);
+            }
+            let literals = rest.get(..len).ok_or(DecodeError::InvalidData)?;
+            iter.nth(len - 1);
+            (TokenKind::Literals(literals), len)
+        } else if self.matches > 0 && self.position < self.size {
+            self.matches -= 1;
+            let lead_cp = *iter.next().ok_or(DecodeError::InvalidData)?;
+            let distance = S7s::read_with_acc(iter, lead_cp as usize & 0x0F)
+                .ok_or(DecodeError::InvalidData)?
+                + 1;
+            let len = match lead_cp >> 4 {
+                0 => S7s::read(iter).ok_or(DecodeError::InvalidData)?,
+                len => len as usize,
+            } + 1;
+            if distance > self.position {
+                return Err(DecodeError::InvalidData);
+            }
+            (
+                TokenKind::Match { distance, len },
+                len.min(self.size - self.position),
+            )
+        } else {
+            if rest.is_empty() || self.position >= self.size {
+                return Ok(None);
+            }
+            let lead = *iter.next().ok_or(DecodeError::InvalidData)?;
+            let literals = match lead & 0x0F {
+                0 => S7s::read(iter).ok_or(DecodeError::InvalidData)?,
+                by => by as usize,
+            };
+            let matches = match lead >> 4 {
+                0 => S7s::read(iter).ok_or(DecodeError::InvalidData)?,
+                lz => lz as usize,
+            };
+            self.literals = literals;
+            self.matches = matches;
+            (TokenKind::Group { literals, matches }, 0)
+        };
+        let token = Token {
+            offset: self.offset,
+            encoded_len: rest.len() - iter.as_slice().len(),
+            position: self.position,
+            kind,
+            decoded_len,
+        };
+        self.offset += token.encoded_len;
+        self.position += decoded_len;
+        Ok(Some(token))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        let result = self._next().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.is_done = true;
+        }
+        result
+    }
+}
+
+impl Stk1 {
+    /// Returns an iterator over the tokens of the compressed data.
+    ///
+    /// `size` is the decompressed size, or `usize::MAX` to read tokens to the end of the input.
+    #[inline]
+    pub fn tokens(input: &[u8], size: usize) -> Tokens<'_> {
+        Tokens {
+            input,
+            offset: 0,
+            position: 0,
+            size,
+            literals: 0,
+            matches: 0,
+            is_done: false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::{Configuration, Stk1, TokenKind};
+
+    #[test]
+    fn tokens() {
+        let input = [b"The quick brown fox jumps over the lazy dog. ".as_slice(); 100].concat();
+        let encoded = Stk1::encode(&input, Configuration::DEFAULT).unwrap();
+
+        let mut decoded = Vec::new();
+        let mut offset = 0;
+        for token in Stk1::tokens(&encoded, input.len()) {
+            let token = token.unwrap();
+            assert_eq!(token.offset, offset);
+            assert_eq!(token.position, decoded.len());
+            offset += token.encoded_len;
+            match token.kind {
+                TokenKind::Group { .. } => {}
+                TokenKind::Literals(literals) => decoded.extend_from_slice(literals),
+                TokenKind::Match { distance, len } => {
+                    for _ in 0..len {
+                        decoded.push(decoded[decoded.len() - distance]);
+                    }
+                }
+            }
+            assert_eq!(decoded.len(), token.position + token.decoded_len());
+        }
+        assert_eq!(offset, encoded.len());
+        assert_eq!(decoded, input);
+        let count = Stk1::tokens(&encoded, input.len()).count();
+        assert_eq!(Stk1::tokens(&encoded, usize::MAX).count(), count);
+
+        // Truncated and invalid data
+        for len in 0..encoded.len() {
+            let result = Stk1::tokens(&encoded[..len], input.len()).collect::<Result<Vec<_>, _>>();
+            let decodable = Stk1::decode_to_vec(&encoded[..len], input.len()).is_ok();
+            assert!(result.is_ok() || !decodable);
+        }
+        // The last match is cut off at the decompressed size
+        let tokens = Stk1::tokens(&[0x11, 0x41, 0x21], 3)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let last = tokens.last().unwrap();
+        assert_eq!(
+            last.kind,
+            TokenKind::Match {
+                distance: 1,
+                len: 3
+            }
+        );
+        assert_eq!(last.position + last.decoded_len(), 3);
+        assert_eq!(tokens.iter().map(|v| v.decoded_len()).sum::<usize>(), 3);
+
+        let mut tokens = Stk1::tokens(&[0x10, 0x01], usize::MAX);
+        assert!(tokens.next().unwrap().is_ok());
+        assert!(tokens.next().unwrap().is_err());
+        assert!(tokens.next().is_none());
+        assert_eq!(tokens.offset(), 2);
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy)]
 pub struct VarSlice<'a, T> {
     source: &'a [T],
     offset: usize,